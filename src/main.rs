@@ -7,26 +7,36 @@ use rustpunk::view::*;
 use rustpunk::gamestate::*;
 use rustpunk::object::*;
 use rustpunk::pos::*;
+use rustpunk::message::*;
+use rustpunk::layout::*;
+use rustpunk::screen::*;
+use rustpunk::item::ItemId;
 
 const LIMIT_FPS: i32 = 50;
 
 struct Game {
-    state: GameState,
+    /// Game in progress, if any
+    state: Option<GameState>,
+    screen: Screen,
+    options: Options,
     root: Root,
     con: Offscreen,
-    view: Option<Box<dyn View>>,
+    /// Open views, the topmost one receives the input
+    views: Vec<Box<dyn View>>,
+    /// Screen cell under the mouse cursor
+    mouse: Pos,
+    /// Map position the player is walking to after a click, and the number
+    /// of enemies in sight. Walking stops when more enemies show up.
+    travel: Option<(Pos, usize)>,
     quit: bool,
 }
 
 impl Game {
     fn run(&mut self) {
-        self.state.update();
         while !(self.root.window_closed() || self.quit) {
             self.handle_keys();
-            self.state.render(&mut self.con);
-            if let Some(view) = &self.view {
-                view.render(&mut self.con);
-            }
+            self.travel();
+            self.render();
             blit(
                 &self.con, 
                 (0, 0), 
@@ -40,14 +50,60 @@ impl Game {
         }
     }
 
+    fn render(&mut self) {
+        match &self.screen {
+            Screen::Title(title)     => title.render(&mut self.con),
+            Screen::Options(options) => options.render(&mut self.con),
+            Screen::GameOver(over)   => over.render(&mut self.con),
+            Screen::Playing          => {
+                let state = self.state.as_ref().expect("No game in progress");
+                state.render(&mut self.con);
+                if self.views.is_empty() {
+                    state.render_hover(&mut self.con, self.mouse);
+                }
+                // Views that show the map hide everything below them
+                let first = self.views.iter().rposition(|x| x.shows_map()).unwrap_or(0);
+                for view in self.views[first..].iter() {
+                    view.render(&mut self.con);
+                }
+            }
+        }
+    }
+
+    fn state(&self) -> &GameState {
+        self.state.as_ref().expect("No game in progress")
+    }
+
+    fn state_mut(&mut self) -> &mut GameState {
+        self.state.as_mut().expect("No game in progress")
+    }
+
     fn handle_keys(&mut self){
-        // Get the last keypress
-        let maybe_key = check_for_event(KEY_PRESS);
-        // Consume all remaining events (hack, because every keypress generates
-        // two events and the flags seem to not have a way to filter those)
-        events().last();
+        // Only the first keypress counts (hack, because every keypress
+        // generates two events and the flags seem to not have a way to filter
+        // those), but all mouse events are handled
+        let mut maybe_key = None;
+        for (flags, event) in events() {
+            match event {
+                Event::Key(key) => if maybe_key.is_none() && flags.contains(KEY_PRESS) {
+                    maybe_key = Some(key);
+                },
+                Event::Mouse(mouse) => self.handle_mouse(mouse),
+            }
+        }
 
-        if let Some((_,Event::Key(key))) = maybe_key {
+        if let Some(mut key) = maybe_key {
+            // Any key stops walking to a clicked tile
+            self.travel = None;
+            // In menus plain letters choose entries and shifted letters work
+            // like the plain ones everywhere else
+            if self.accepts_hotkeys() && key.printable.is_ascii_alphabetic() {
+                if !key.shift && key.printable.is_ascii_lowercase() {
+                    self.handle_command(Command::Hotkey(key.printable));
+                    return;
+                }
+                key.printable = key.printable.to_ascii_lowercase();
+            }
             let command = match key {
                 Key { printable: 'h', .. }         => Some(Command::Move(Dir::W)),
                 Key { code: KeyCode::Left, .. }    => Some(Command::Move(Dir::W)),
@@ -59,9 +115,21 @@ impl Game {
                 Key { code: KeyCode::Down, .. }    => Some(Command::Move(Dir::S)),
                 Key { printable: '.', .. }         => Some(Command::Wait),
                 Key { printable: 'g', .. }         => Some(Command::GetItem),
+                Key { printable: 's', .. }         => Some(Command::Sneak),
                 Key { printable: 'i', .. }         => Some(Command::OpenInventory),
+                Key { printable: 'f', .. }         => Some(Command::Fire),
+                Key { printable: 'e', .. }         => Some(Command::Equip),
+                Key { printable: 'o', .. }         => Some(Command::Open),
+                Key { printable: 'd', .. }         => Some(Command::Drop),
+                Key { printable: 'x', .. }         => Some(Command::Examine),
+                Key { printable: ';', .. }         => Some(Command::Look),
+                Key { printable: 'm', .. }         => Some(Command::MessageLog),
+                Key { printable: 'c', .. }         => Some(Command::CharacterSheet),
+                Key { code: KeyCode::Spacebar, .. } => Some(Command::Mark),
                 Key { code: KeyCode::Escape, .. }  => Some(Command::CloseView),
                 Key { code: KeyCode::Enter, .. }   => Some(Command::Select),
+                Key { code: KeyCode::PageUp, .. }  => Some(Command::PageUp),
+                Key { code: KeyCode::PageDown, .. } => Some(Command::PageDown),
                 _                                  => None,
             };
 
@@ -69,47 +137,257 @@ impl Game {
         }
     }
 
-    fn handle_command(&mut self, command: Command){
-        let new_command;
-        if let Some(ref mut view) = self.view {
-            if let Some(c) = view.handle_command(&mut self.state, command) {
-                new_command = c;
+    fn handle_mouse(&mut self, mouse: Mouse) {
+        self.mouse = Pos::new(mouse.cx as i32, mouse.cy as i32);
+        if mouse.wheel_up {
+            self.handle_command(Command::ScrollUp);
+        }
+        if mouse.wheel_down {
+            self.handle_command(Command::ScrollDown);
+        }
+        if mouse.lbutton_pressed {
+            self.handle_command(Command::Click(self.mouse));
+        }
+    }
+
+    /// Takes the next step towards the clicked tile, if the player is
+    /// walking somewhere.
+    fn travel(&mut self) {
+        let (target, enemies) = match self.travel {
+            Some(travel) => travel,
+            None         => return,
+        };
+        if !self.views.is_empty() || !matches!(self.screen, Screen::Playing) {
+            self.travel = None;
+            return;
+        }
+        let (pos, step, seen) = {
+            let state = self.state();
+            let pos = state.get_player().pos;
+            (pos, state.path_step(pos, target), state.visible_enemies().len())
+        };
+        match step {
+            Some(dir) if pos != target && seen <= enemies => {
+                if !self.state().is_walkable(pos + dir.to_pos()) {
+                    self.travel = None;
+                    return;
+                }
+                self.travel = Some((target, seen));
+                self.handle_command(Command::Move(dir));
+                // Stop if the step was refused, e.g. when carrying too much
+                let moved = self.state.as_ref().is_some_and(|x| x.get_player().pos != pos);
+                if !moved {
+                    self.travel = None;
+                }
+            }
+            _ => self.travel = None,
+        }
+    }
+
+    /// Starts walking to the map tile shown at the screen position `pos`.
+    fn start_travel(&mut self, pos: Pos) {
+        if !VIEWPORT.contains(pos) {
+            return;
+        }
+        let travel = {
+            let state = self.state();
+            let target = pos + state.cam_pos();
+            if state.is_explored(target) && !state.is_solid(target) {
+                Some((target, state.visible_enemies().len()))
             } else {
-                return;
+                None
+            }
+        };
+        self.travel = travel;
+    }
+
+    /// Whether the current screen or view is a menu that takes letters as
+    /// hotkeys.
+    fn accepts_hotkeys(&self) -> bool {
+        match self.screen {
+            Screen::Title(_) | Screen::Options(_) => true,
+            Screen::GameOver(_)                   => false,
+            Screen::Playing => self.views.last().is_some_and(|x| x.accepts_hotkeys()),
+        }
+    }
+
+    /// Passes the command to the current screen and switches screens if
+    /// asked to.
+    fn handle_command(&mut self, command: Command) {
+        let next = match self.screen {
+            Screen::Title(ref mut title)     => title.handle_command(command),
+            Screen::Options(ref mut options) => options.handle_command(&mut self.options, command),
+            Screen::GameOver(ref mut over)   => over.handle_command(command),
+            Screen::Playing                  => {
+                self.handle_game_command(command);
+                if self.state().is_over() {
+                    self.screen = Screen::GameOver(GameOverScreen::new(self.state()));
+                    self.state = None;
+                    self.views.clear();
+                }
+                None
+            }
+        };
+        match next {
+            Some(Command::NewGame) => {
+                let mut state = GameState::new();
+                state.set_message_cap(self.options.message_cap);
+                self.state = Some(state);
+                self.views.clear();
+                self.screen = Screen::Playing;
+            }
+            Some(Command::Continue) => self.screen = Screen::Playing,
+            Some(Command::Options) => self.screen = Screen::Options(OptionsScreen::new(&self.options)),
+            Some(Command::MainMenu) => self.main_menu(),
+            Some(Command::ExitGame) => self.quit(),
+            _ => {}
+        }
+    }
+
+    /// Passes the command to the topmost view. Commands the view gives back,
+    /// or all commands if there is no view, are carried out by the game.
+    fn handle_game_command(&mut self, command: Command) {
+        if let Some(view) = self.views.last_mut() {
+            let state = self.state.as_mut().expect("No game in progress");
+            if let Some(c) = view.handle_command(state, command) {
+                self.run_command(c);
             }
         } else {
-            new_command = command;
+            self.run_command(command);
         }
-        match new_command {
-            Command::Move(dir) => self.state.player_action(Action::Move(dir)),
-            Command::Wait => self.state.player_action(Action::Idle),
+    }
+
+    fn run_command(&mut self, command: Command) {
+        match command {
+            Command::Move(dir) => self.state_mut().player_action(Action::Move(dir)),
+            Command::Wait => self.state_mut().player_action(Action::Idle),
+            Command::Sneak => self.state_mut().toggle_sneak(),
             Command::GetItem => self.open_pickup_menu(),
-            Command::CloseView => self.back(),
+            Command::CloseView => self.back(false),
+            Command::Finish => self.back(true),
             Command::OpenInventory => self.open_inventory(),
-            Command::ExitGame => self.quit(),
+            Command::Fire => self.open_targeting(),
+            Command::Aimed(aim, target) => self.state_mut().player_action(aim.action(target)),
+            Command::Target(aim) => {
+                let targeting_view = TargetingView::new(self.state(), aim);
+                self.push_view(targeting_view);
+            }
+            Command::Open => {
+                let pos = self.state().get_player().pos;
+                match self.state().container_at(pos) {
+                    Some(id) => self.open_container(id),
+                    None     => {
+                        let msg = Message::warning("There is nothing here to open.".to_string());
+                        self.state_mut().add_message(msg);
+                    }
+                }
+            }
+            Command::OpenContainer(id) => self.open_container(id),
+            Command::Look => self.push_view(LookView::new(self.state())),
+            Command::MessageLog => self.push_view(MessageLogView::new(self.state())),
+            Command::CharacterSheet => self.push_view(CharacterSheetView::new(self.state())),
+            Command::Click(pos) => self.start_travel(pos),
+            // Scrolling up over the map brings up the message history
+            Command::ScrollUp => self.push_view(MessageLogView::new(self.state())),
+            Command::ShowItem(id) => {
+                if let Some(item) = self.state().find_item(id) {
+                    self.push_view(ExamineView::new(&item));
+                }
+            }
             _ => {}
         }
     }
 
+    fn push_view(&mut self, view: impl View + 'static) {
+        self.views.push(Box::new(view));
+    }
+
     fn open_inventory(&mut self) {
-        if self.view.is_none() {
-            let inv_view = InventoryView::new(&self.state.get_player().inventory);
-            self.view = Some(Box::new(inv_view));
+        let inv_view = InventoryView::new(&self.state().get_player());
+        self.push_view(inv_view);
+    }
+
+    fn open_targeting(&mut self) {
+        // Without ammunition, fall back to throwing so no turn is wasted
+        let (aim, out_of_ammo) = {
+            let player = self.state().get_player();
+            let aim = if player.can_shoot() {
+                Some(Aim::Shoot)
+            } else {
+                player.throwable().map(Aim::Throw)
+            };
+            (aim, player.equipment.firearm().is_some())
+        };
+        match aim {
+            Some(aim) => {
+                let targeting_view = TargetingView::new(self.state(), aim);
+                self.push_view(targeting_view);
+            }
+            None => {
+                let text = if out_of_ammo {
+                    "You are out of ammunition and have nothing to throw."
+                } else {
+                    "You have nothing to shoot or throw."
+                };
+                let msg = Message::warning(text.to_string());
+                self.state_mut().add_message(msg);
+            }
         }
     }
 
     fn open_pickup_menu(&mut self) {
-        let invs = self.state.objects_at(self.state.get_player().pos);
-        let invs_fil: Vec<&i32> = invs.iter().filter(|x| **x != 0).collect();
-        if invs_fil.len() > 0 {
-            let other_idx = invs_fil.get(0).expect("There is supposed to be at least one element");
-            let pickup_view = PickupView::new(&self.state, **other_idx);
-            self.view = Some(Box::new(pickup_view));
+        let pos = self.state().get_player().pos;
+        if self.state().items_at(pos).is_some() {
+            let pickup_view = PickupView::new(self.state());
+            self.push_view(pickup_view);
+        } else {
+            let msg = Message::warning("There is nothing here to pick up.".to_string());
+            self.state_mut().add_message(msg);
         }
     }
     
-    fn back(&mut self) {
-        self.view = None;
+    fn open_container(&mut self, id: ItemId) {
+        if !self.state_mut().open_container(id) {
+            return;
+        }
+        let name = {
+            let player = self.state().get_player();
+            player.inventory
+                .get_item(id)
+                .or(self.state().items_at(player.pos).and_then(|x| x.get_item(id)))
+                .map(|x| x.name.clone())
+                .unwrap_or_default()
+        };
+        let container_view = PickupView::container(self.state(), id, name);
+        self.push_view(container_view);
+    }
+
+    /// Closes the topmost view and passes its result to the parent view, or
+    /// to the game if there is none. Leaves for the main menu if there is no
+    /// view open.
+    fn back(&mut self, done: bool) {
+        let child = match self.views.pop() {
+            Some(child) => child,
+            None        => return self.main_menu(),
+        };
+        // Only views that finished their job hand back a result
+        let result = if done { child.result() } else { None };
+        if let Some(parent) = self.views.last_mut() {
+            let state = self.state.as_mut().expect("No game in progress");
+            if let Some(c) = parent.child_closed(state, result) {
+                self.run_command(c);
+            }
+        } else if let Some(c) = result {
+            // Views opened from the map hand their result to the game
+            self.run_command(c);
+        }
+    }
+
+    fn main_menu(&mut self) {
+        if let Some(state) = &mut self.state {
+            state.set_message_cap(self.options.message_cap);
+        }
+        self.screen = Screen::Title(TitleScreen::new(self.state.is_some()));
     }
 
     fn quit(&mut self) {
@@ -128,9 +406,13 @@ fn main() {
     let mut game = Game { 
         root: root, 
         con: Offscreen::new(SCREEN_WIDTH, SCREEN_HEIGHT),
-        state: GameState::new(),
+        state: None,
+        screen: Screen::Title(TitleScreen::new(false)),
+        options: Options::new(),
         quit: false,
-        view: None,
+        views: vec![],
+        mouse: Pos::new(-1, -1),
+        travel: None,
     };
     tcod::system::set_fps(LIMIT_FPS);
     game.run();
@@ -5,3 +5,10 @@ pub mod tile;
 pub mod message;
 pub mod view;
 pub mod item;
+pub mod ai;
+pub mod perception;
+pub mod combat;
+pub mod status;
+pub mod layout;
+pub mod screen;
+pub mod menu;
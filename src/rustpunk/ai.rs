@@ -0,0 +1,389 @@
+use crate::rustpunk::gamestate::*;
+use crate::rustpunk::item::*;
+use crate::rustpunk::object::*;
+use crate::rustpunk::perception::*;
+use crate::rustpunk::pos::*;
+
+use std::iter::Peekable;
+use std::vec::IntoIter;
+
+use tcod::random::Rng;
+
+/// Result of evaluating a behavior tree node.
+#[derive(Clone, Copy, Debug)]
+pub enum Status {
+    Success,
+    Failure,
+    /// The node decided on an action, which ends the evaluation.
+    Act(Action),
+}
+
+/// Checks that a brain can make about itself and its surroundings.
+#[derive(Clone, Copy, Debug)]
+pub enum Condition {
+    /// A hostile character is in sight.
+    SeeHostile,
+    /// The brain remembers where a target was last seen or heard.
+    KnowsTarget,
+    /// The remembered target is right next to us.
+    NextToTarget,
+    /// Health is at or below the given percentage of maximum health.
+    LowHealth(i32),
+    /// There is an item in the inventory that restores health.
+    HasHealingItem,
+}
+
+/// Things a brain can decide to do.
+#[derive(Clone, Copy, Debug)]
+pub enum Task {
+    /// Walk along the shortest path to the remembered target. Falls back to
+    /// walking straight towards it if there is no path.
+    PathToTarget,
+    /// Attack the remembered target if it is adjacent.
+    Attack,
+    /// Use the first item in the inventory that restores health.
+    UseHealingItem,
+    /// Move away from the remembered target.
+    Flee,
+    /// Take a step in a random direction.
+    Wander,
+    Idle,
+}
+
+/// A node of a behavior tree.
+#[derive(Clone, Debug)]
+pub enum Node {
+    /// Evaluates children in order until one of them does not fail.
+    Selector(Vec<Node>),
+    /// Evaluates children in order until one of them does not succeed.
+    Sequence(Vec<Node>),
+    /// Swaps the success and failure of the child.
+    Not(Box<Node>),
+    Check(Condition),
+    Do(Task),
+}
+
+impl Node {
+    pub fn eval(&self, brain: &Brain, obj: &Character, gs: &GameState) -> Status {
+        match self {
+            Node::Selector(children) => {
+                for child in children {
+                    match child.eval(brain, obj, gs) {
+                        Status::Failure => continue,
+                        s               => return s,
+                    }
+                }
+                Status::Failure
+            }
+            Node::Sequence(children) => {
+                for child in children {
+                    match child.eval(brain, obj, gs) {
+                        Status::Success => continue,
+                        s               => return s,
+                    }
+                }
+                Status::Success
+            }
+            Node::Not(child) => {
+                match child.eval(brain, obj, gs) {
+                    Status::Success => Status::Failure,
+                    Status::Failure => Status::Success,
+                    s               => s,
+                }
+            }
+            Node::Check(cond) => {
+                if cond.check(brain, obj, gs) {
+                    Status::Success
+                } else {
+                    Status::Failure
+                }
+            }
+            Node::Do(task) => match task.run(brain, obj, gs) {
+                Some(action) => Status::Act(action),
+                None         => Status::Failure,
+            },
+        }
+    }
+}
+
+impl Condition {
+    fn check(&self, brain: &Brain, obj: &Character, gs: &GameState) -> bool {
+        match self {
+            Condition::SeeHostile => find_hostile(obj, gs).is_some(),
+            Condition::KnowsTarget => brain.target().is_some(),
+            Condition::NextToTarget => match brain.target() {
+                Some(p) => (p - obj.pos).to_dir().is_some(),
+                None    => false,
+            },
+            Condition::LowHealth(pct) => obj.health * 100 <= obj.max_health() * pct,
+            Condition::HasHealingItem => find_healing_item(obj).is_some(),
+        }
+    }
+}
+
+impl Task {
+    fn run(&self, brain: &Brain, obj: &Character, gs: &GameState) -> Option<Action> {
+        match self {
+            Task::PathToTarget => {
+                let target = brain.target()?;
+                match gs.path_step(obj.pos, target) {
+                    Some(dir) => Some(Action::Move(dir)),
+                    None      => Some(obj.move_towards(target)),
+                }
+            }
+            Task::Attack => {
+                let target = brain.target()?;
+                let dir = (target - obj.pos).to_dir()?;
+                Some(Action::Move(dir))
+            }
+            Task::UseHealingItem => {
+                find_healing_item(obj).map(Action::UseItem)
+            }
+            Task::Flee => {
+                let target = brain.target()?;
+                let away = obj.pos + (obj.pos - target);
+                let dir = obj.pos.dir_towards(away)?;
+                if gs.is_walkable(obj.pos + dir.to_pos()) {
+                    Some(Action::Move(dir))
+                } else {
+                    None
+                }
+            }
+            Task::Wander => {
+                let rng = Rng::get_instance();
+                let dir = match rng.get_int(0, 3) {
+                    0 => Dir::N,
+                    1 => Dir::E,
+                    2 => Dir::S,
+                    _ => Dir::W,
+                };
+                if gs.is_walkable(obj.pos + dir.to_pos()) {
+                    Some(Action::Move(dir))
+                } else {
+                    None
+                }
+            }
+            Task::Idle => Some(Action::Idle),
+        }
+    }
+}
+
+/// Behavior tree together with the things the NPC remembers.
+#[derive(Clone, Debug)]
+pub struct Brain {
+    pub root: Node,
+    /// Where the NPC last saw or heard a hostile character
+    pub memory: Option<Memory>,
+}
+
+impl Brain {
+    pub fn new(root: Node) -> Self {
+        Brain {
+            root: root,
+            memory: None,
+        }
+    }
+
+    pub fn target(&self) -> Option<Pos> {
+        self.memory.map(|m| m.pos)
+    }
+
+    /// Updates what the brain knows about its surroundings.
+    pub fn update(&mut self, obj: &Character, gs: &GameState) {
+        if let Some(p) = find_hostile(obj, gs) {
+            self.memory = Some(Memory::new(p, SIGHT_MEMORY));
+        } else if let Some(p) = hear_hostile(obj, gs) {
+            self.memory = Some(Memory::new(p, NOISE_MEMORY));
+        } else if let Some(ref mut m) = self.memory {
+            m.turns_left -= 1;
+            // Forget the target if the memory has faded or we reached the
+            // place where we last noticed it and there is nobody here anymore
+            if m.turns_left <= 0 || m.pos == obj.pos {
+                self.memory = None;
+            }
+        }
+    }
+
+    pub fn next_action(&self, obj: &Character, gs: &GameState) -> Action {
+        match self.root.eval(self, obj, gs) {
+            Status::Act(action) => action,
+            _                   => Action::Idle,
+        }
+    }
+}
+
+/// Behavior trees of the character templates.
+const BRAINS: &str = include_str!("../../data/brains.txt");
+
+type Tokens = Peekable<IntoIter<String>>;
+
+/// Returns the behavior tree of the named character template, as defined in
+/// `data/brains.txt`.
+pub fn brain_template(name: &str) -> Result<Node, String> {
+    find_brain(BRAINS, name)
+}
+
+/// Parses the brain definitions in `src` and returns the tree named `name`.
+fn find_brain(src: &str, name: &str) -> Result<Node, String> {
+    let mut tokens = tokenize(src).into_iter().peekable();
+    while let Some(template) = tokens.next() {
+        let tree = parse_node(&mut tokens)?;
+        if template == name {
+            return Ok(tree);
+        }
+    }
+    Err(format!("Unknown brain template '{}'", name))
+}
+
+/// Splits brain definitions into parentheses and words, skipping comments.
+fn tokenize(src: &str) -> Vec<String> {
+    src.lines()
+        .map(|line| line.split('#').next().unwrap_or(""))
+        .flat_map(|line| {
+            line.replace('(', " ( ")
+                .replace(')', " ) ")
+                .split_whitespace()
+                .map(String::from)
+                .collect::<Vec<String>>()
+        })
+        .collect()
+}
+
+fn next_token(tokens: &mut Tokens) -> Result<String, String> {
+    tokens.next().ok_or_else(|| "Unexpected end of brain data".to_string())
+}
+
+fn parse_node(tokens: &mut Tokens) -> Result<Node, String> {
+    let token = next_token(tokens)?;
+    if token != "(" {
+        return parse_leaf(&token);
+    }
+    let head = next_token(tokens)?;
+    match head.as_str() {
+        "selector"   => Ok(Node::Selector(parse_children(tokens)?)),
+        "sequence"   => Ok(Node::Sequence(parse_children(tokens)?)),
+        "not"        => {
+            let mut children = parse_children(tokens)?;
+            if children.len() != 1 {
+                return Err("'not' takes exactly one child".to_string());
+            }
+            Ok(Node::Not(Box::new(children.remove(0))))
+        }
+        "low_health" => {
+            let arg = next_token(tokens)?;
+            let pct = arg
+                .parse()
+                .map_err(|_| format!("Bad health percentage '{}'", arg))?;
+            if next_token(tokens)? != ")" {
+                return Err("'low_health' takes exactly one number".to_string());
+            }
+            Ok(Node::Check(Condition::LowHealth(pct)))
+        }
+        _ => Err(format!("Unknown node '{}'", head)),
+    }
+}
+
+/// Parses nodes up to and including the closing parenthesis of a list.
+fn parse_children(tokens: &mut Tokens) -> Result<Vec<Node>, String> {
+    let mut children = vec![];
+    loop {
+        match tokens.peek().map(String::as_str) {
+            Some(")") => {
+                tokens.next();
+                return Ok(children);
+            }
+            Some(_) => children.push(parse_node(tokens)?),
+            None    => return Err("Missing ')' in brain data".to_string()),
+        }
+    }
+}
+
+fn parse_leaf(name: &str) -> Result<Node, String> {
+    let node = match name {
+        "see_hostile"      => Node::Check(Condition::SeeHostile),
+        "knows_target"     => Node::Check(Condition::KnowsTarget),
+        "next_to_target"   => Node::Check(Condition::NextToTarget),
+        "has_healing_item" => Node::Check(Condition::HasHealingItem),
+        "path_to_target"   => Node::Do(Task::PathToTarget),
+        "attack"           => Node::Do(Task::Attack),
+        "use_healing_item" => Node::Do(Task::UseHealingItem),
+        "flee"             => Node::Do(Task::Flee),
+        "wander"           => Node::Do(Task::Wander),
+        "idle"             => Node::Do(Task::Idle),
+        _ => return Err(format!("Unknown node '{}'", name)),
+    };
+    Ok(node)
+}
+
+/// Finds the position of the closest visible hostile character.
+fn find_hostile(obj: &Character, gs: &GameState) -> Option<Pos> {
+    gs.others()
+        .iter()
+        .filter(|o| o.alive && obj.faction.is_hostile(o.faction))
+        .filter(|o| gs.can_see(obj, o))
+        .min_by_key(|o| obj.pos.distance(o.pos))
+        .map(|o| o.pos)
+}
+
+/// Finds the position of the closest audible noise made by a hostile
+/// character.
+fn hear_hostile(obj: &Character, gs: &GameState) -> Option<Pos> {
+    gs.noises()
+        .iter()
+        .filter(|n| obj.faction.is_hostile(n.faction))
+        .filter(|n| n.is_audible(gs, obj.pos))
+        .min_by_key(|n| obj.pos.distance(n.pos))
+        .map(|n| n.pos)
+}
+
+/// Finds the id of the first inventory item that restores health.
+fn find_healing_item(obj: &Character) -> Option<ItemId> {
+    obj.inventory.items
+        .iter()
+        .find(|item| item.on_use.iter().any(|e| match e {
+            ItemEffect::ChangeHealth(amt) => *amt > 0,
+            _                             => false,
+        }))
+        .map(|item| item.id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn templates_in_data_parse() {
+        assert!(brain_template("wolf").is_ok());
+        assert!(brain_template("raider").is_ok());
+        assert!(brain_template("dragon").is_err());
+    }
+
+    #[test]
+    fn parses_nested_nodes() {
+        let src = "# comment\ntest (selector (sequence (low_health 30) flee) (not see_hostile))";
+        match find_brain(src, "test") {
+            Ok(Node::Selector(children)) => {
+                assert_eq!(children.len(), 2);
+                match &children[0] {
+                    Node::Sequence(seq) => match seq[0] {
+                        Node::Check(Condition::LowHealth(30)) => {}
+                        ref other => panic!("Expected low_health, got {:?}", other),
+                    },
+                    other => panic!("Expected a sequence, got {:?}", other),
+                }
+                assert!(matches!(children[1], Node::Not(_)));
+            }
+            other => panic!("Expected a selector, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_trees() {
+        assert!(find_brain("test (selector idle", "test").is_err());
+        assert!(find_brain("test (not idle flee)", "test").is_err());
+        assert!(find_brain("test (low_health many)", "test").is_err());
+        assert!(find_brain("test (low_health 10 20)", "test").is_err());
+        assert!(find_brain("test dance", "test").is_err());
+        assert!(find_brain("test", "test").is_err());
+    }
+}
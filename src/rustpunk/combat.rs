@@ -0,0 +1,186 @@
+use core::cmp::max;
+
+use std::fmt;
+
+use crate::rustpunk::item::WearLoc;
+use crate::rustpunk::object::Character;
+
+use tcod::random::Rng;
+
+/// Chance to hit in percent when attacker agility equals defender evasion.
+pub const BASE_HIT_CHANCE: i32 = 75;
+/// Change in hit chance for each point of difference between attacker
+/// agility and defender evasion.
+pub const HIT_CHANCE_PER_POINT: i32 = 5;
+pub const MIN_HIT_CHANCE: i32 = 5;
+pub const MAX_HIT_CHANCE: i32 = 95;
+
+/// Chance of a critical hit in percent with average agility.
+pub const BASE_CRIT_CHANCE: i32 = 5;
+/// Change in critical hit chance for each point of agility above average.
+pub const CRIT_CHANCE_PER_POINT: i32 = 1;
+pub const CRIT_MULTIPLIER: i32 = 2;
+/// Number of ticks a critical hit makes the victim bleed.
+pub const CRIT_BLEEDING: i32 = 4;
+
+/// Damage roll of the form `2d6+1`.
+#[derive(Clone, Copy, Debug)]
+pub struct Dice {
+    pub count: i32,
+    pub sides: i32,
+    pub bonus: i32,
+}
+
+impl Dice {
+    pub fn new(count: i32, sides: i32, bonus: i32) -> Self {
+        Dice {
+            count: count,
+            sides: sides,
+            bonus: bonus,
+        }
+    }
+
+    pub fn roll(self) -> i32 {
+        let rng = Rng::get_instance();
+        let total: i32 = (0..self.count).map(|_| rng.get_int(1, self.sides)).sum();
+        max(0, total + self.bonus)
+    }
+
+    pub fn max(self) -> i32 {
+        max(0, self.count * self.sides + self.bonus)
+    }
+}
+
+impl fmt::Display for Dice {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}d{}", self.count, self.sides)?;
+        if self.bonus > 0 {
+            write!(f, "+{}", self.bonus)?;
+        } else if self.bonus < 0 {
+            write!(f, "{}", self.bonus)?;
+        }
+        Ok(())
+    }
+}
+
+/// Outcome of a single attack.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AttackResult {
+    Miss,
+    Hit(i32),
+    Crit(i32),
+}
+
+/// Chance in percent that an attacker with the given agility hits a defender
+/// with the given evasion.
+pub fn hit_chance(attacker_agi: i32, defender_evasion: i32) -> i32 {
+    let chance = BASE_HIT_CHANCE + (attacker_agi - defender_evasion) * HIT_CHANCE_PER_POINT;
+    chance.clamp(MIN_HIT_CHANCE, MAX_HIT_CHANCE)
+}
+
+/// Chance in percent that a hit from an attacker with the given agility is
+/// critical.
+pub fn crit_chance(attacker_agi: i32) -> i32 {
+    max(0, BASE_CRIT_CHANCE + (attacker_agi - 10) * CRIT_CHANCE_PER_POINT)
+}
+
+/// Damage left after armor has absorbed its share.
+pub fn absorb(damage: i32, armor: i32) -> i32 {
+    max(0, damage - max(0, armor))
+}
+
+/// Decides the outcome of an attack from already rolled numbers. `hit_roll`
+/// and `crit_roll` are percentile rolls between 1 and 100.
+pub fn resolve(
+    hit_chance: i32,
+    crit_chance: i32,
+    hit_roll: i32,
+    crit_roll: i32,
+    damage: i32,
+    armor: i32) -> AttackResult {
+
+    if hit_roll > hit_chance {
+        AttackResult::Miss
+    } else if crit_roll <= crit_chance {
+        // Critical hits ignore armor
+        AttackResult::Crit(damage * CRIT_MULTIPLIER)
+    } else {
+        AttackResult::Hit(absorb(damage, armor))
+    }
+}
+
+/// Picks the body part that an attack lands on. Hits are most likely to land
+/// on the torso.
+pub fn hit_location(roll: i32) -> WearLoc {
+    match roll {
+        1..=10  => WearLoc::Head,
+        11..=25 => WearLoc::Shoulders,
+        26..=65 => WearLoc::Torso,
+        66..=80 => WearLoc::Hands,
+        _       => WearLoc::Legs,
+    }
+}
+
+/// Rolls an attack of `attacker` against `defender` that deals `damage`
+/// damage if it hits. Returns the outcome and the body part that was hit.
+pub fn roll_attack(attacker: &Character, damage: i32, defender: &Character) -> (AttackResult, WearLoc) {
+    let rng = Rng::get_instance();
+    let agi = attacker.stats().agi;
+    let loc = hit_location(rng.get_int(1, 100));
+    let result = resolve(
+        hit_chance(agi, defender.evasion()),
+        crit_chance(agi),
+        rng.get_int(1, 100),
+        rng.get_int(1, 100),
+        damage,
+        defender.armor_at(loc));
+    (result, loc)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hit_chance_is_clamped() {
+        assert_eq!(hit_chance(10, 10), BASE_HIT_CHANCE);
+        assert_eq!(hit_chance(11, 10), BASE_HIT_CHANCE + HIT_CHANCE_PER_POINT);
+        assert_eq!(hit_chance(100, 0), MAX_HIT_CHANCE);
+        assert_eq!(hit_chance(0, 100), MIN_HIT_CHANCE);
+    }
+
+    #[test]
+    fn crit_chance_is_never_negative() {
+        assert_eq!(crit_chance(10), BASE_CRIT_CHANCE);
+        assert_eq!(crit_chance(12), BASE_CRIT_CHANCE + 2 * CRIT_CHANCE_PER_POINT);
+        assert_eq!(crit_chance(0), 0);
+    }
+
+    #[test]
+    fn armor_absorbs_damage() {
+        assert_eq!(absorb(10, 3), 7);
+        assert_eq!(absorb(3, 10), 0);
+        assert_eq!(absorb(5, -2), 5);
+    }
+
+    #[test]
+    fn roll_above_hit_chance_misses() {
+        assert_eq!(resolve(50, 100, 51, 1, 10, 0), AttackResult::Miss);
+        assert_eq!(resolve(50, 0, 50, 100, 10, 3), AttackResult::Hit(7));
+    }
+
+    #[test]
+    fn crits_ignore_armor() {
+        assert_eq!(resolve(50, 5, 1, 5, 10, 100), AttackResult::Crit(10 * CRIT_MULTIPLIER));
+        assert_eq!(resolve(50, 5, 1, 6, 10, 100), AttackResult::Hit(0));
+    }
+
+    #[test]
+    fn hit_locations_cover_every_roll() {
+        assert_eq!(hit_location(1), WearLoc::Head);
+        assert_eq!(hit_location(25), WearLoc::Shoulders);
+        assert_eq!(hit_location(26), WearLoc::Torso);
+        assert_eq!(hit_location(80), WearLoc::Hands);
+        assert_eq!(hit_location(100), WearLoc::Legs);
+    }
+}
@@ -1,30 +1,63 @@
 use core::cmp::min;
 use crate::rustpunk::item::ItemEffect;
 use crate::rustpunk::item::Item;
+use crate::rustpunk::item::Inventory;
+use crate::rustpunk::item::ItemId;
 use core::cell::*;
 use std::cmp::Ordering;
+use std::collections::HashMap;
 use core::cmp::max;
 use array2d::Array2D;
 
 use crate::rustpunk::tile::Tile;
 use crate::rustpunk::object::*;
 use crate::rustpunk::pos::*;
-use crate::rustpunk::message::Message;
+use crate::rustpunk::message::*;
+use crate::rustpunk::perception::*;
+use crate::rustpunk::status::*;
+use crate::rustpunk::layout::*;
 
 use tcod::line::*;
 use tcod::console::*;
 use tcod::colors::*;
 use tcod::random::Rng;
 use tcod::map::FovAlgorithm;
+use tcod::pathfinding::AStar;
 
-const MAP_SIZE: i32 = 128;
-const VIEWPORT_WIDTH: i32 = 80;
-const VIEWPORT_HEIGHT: i32 = 50;
-const MSG_DISPLAY_COUNT: i32 = 5;
-const HEALTH_BAR_WIDTH: i32 = 20;
+pub const MAP_SIZE: i32 = 128;
+/// How far items without a range of their own can be thrown.
+pub const THROW_RANGE: i32 = 6;
+/// Energy an object needs to have to take an action.
+pub const ACTION_ENERGY: i32 = 100;
+const HEALTH_BAR_WIDTH: i32 = SIDEBAR_WIDTH - 6;
+const ENEMY_BAR_WIDTH: i32 = 6;
+/// Rows at the bottom of the sidebar that describe the tile under the mouse
+const HOVER_HEIGHT: i32 = 8;
 const HEALTH_BAR_BG_COLOR: Color = DARKEST_RED;
 const HEALTH_BAR_FG_COLOR: Color = DARK_RED;
-const DEFAULT_BACKGROUND_COLOR: Color = GREY;
+
+/// Record of how the game went, shown once the player has died.
+#[derive(Clone, Debug, Default)]
+pub struct Statistics {
+    /// Characters killed by the player
+    pub kills: u32,
+    /// Damage the player has dealt to others
+    pub damage_dealt: i32,
+    pub cause_of_death: Option<String>,
+}
+
+impl Statistics {
+    /// Notes down the death of the character at index `victim` at the hands
+    /// of the character at index `killer`.
+    fn record_kill(&mut self, killer: usize, victim: usize, cause: String) {
+        if killer == 0 && victim != 0 {
+            self.kills += 1;
+        }
+        if victim == 0 {
+            self.cause_of_death = Some(cause);
+        }
+    }
+}
 
 /// Map and related data.
 pub struct Map {
@@ -68,7 +101,14 @@ impl Map {
 pub struct GameState {
     map: Map,
     objects: Vec<RefCell<Character>>,
-    messages: Vec<Message>,
+    /// Items lying on the ground, by tile
+    ground: HashMap<Pos, Inventory>,
+    messages: MessageLog,
+    noises: Vec<Noise>,
+    turn: u32,
+    /// Dungeon level the player is on
+    depth: i32,
+    stats: Statistics,
 }
 
 impl GameState {
@@ -78,9 +118,15 @@ impl GameState {
         let mut gs = GameState {
             map: map,
             objects: Vec::new(),
-            messages: Vec::new(),
+            ground: HashMap::new(),
+            messages: MessageLog::new(DEFAULT_MESSAGE_CAP),
+            noises: Vec::new(),
+            turn: 0,
+            depth: 1,
+            stats: Statistics::default(),
         };
         gs.populate();
+        gs.update_fov();
         gs
     }
 
@@ -107,94 +153,181 @@ impl GameState {
                 }
             }
         }
+        for _ in 1..10 {
+            for _ in 1..1000 {
+                let pos = Pos::new(
+                    rng.get_int(0, MAP_SIZE-1), 
+                    rng.get_int(0, MAP_SIZE-1));
+                if self.is_walkable(pos) {
+                    self.objects.push(RefCell::new(Character::raider(pos)));
+                    break
+                }
+            }
+        }
         for _ in 1..50 {
             for _ in 1..1000 {
                 let pos = Pos::new(
                     rng.get_int(0, MAP_SIZE-1), 
                     rng.get_int(0, MAP_SIZE-1));
                 if self.is_walkable(pos) {
-                    self.objects.push(RefCell::new(Character::healing_potion(pos)));
+                    self.drop_item_at(pos, Box::new(Item::healing_potion()));
+                    break
+                }
+            }
+        }
+        for _ in 1..30 {
+            for _ in 1..1000 {
+                let pos = Pos::new(
+                    rng.get_int(0, MAP_SIZE-1), 
+                    rng.get_int(0, MAP_SIZE-1));
+                if self.is_walkable(pos) {
+                    self.drop_item_at(pos, Box::new(random_loot()));
+                    break
+                }
+            }
+        }
+        // Containers with a few items inside
+        let containers = [
+            Item::crate_box,
+            Item::crate_box,
+            Item::locker,
+            Item::vending_machine,
+        ];
+        for _ in 1..20 {
+            for _ in 1..1000 {
+                let pos = Pos::new(
+                    rng.get_int(0, MAP_SIZE-1), 
+                    rng.get_int(0, MAP_SIZE-1));
+                if self.is_walkable(pos) {
+                    let make_container = containers[rng.get_int(0, containers.len() as i32 - 1) as usize];
+                    let items = (0..rng.get_int(1, 4)).map(|_| random_loot()).collect();
+                    self.drop_item_at(pos, Box::new(make_container(items)));
                     break
                 }
             }
         }
     }
 
-    /// Advances the game state by one tick.
+    /// Carries out the action of the player and lets time pass until the
+    /// player has enough energy to act again.
     pub fn update(&mut self) {
-        // Update objects
-        for i in 0..self.objects.len() {
-            let ref mut o = self.objects[i].borrow_mut();
-            // Do nothing if object is dead
-            if !o.alive {
+        self.act(0);
+        loop {
+            {
+                let player = self.get_player();
+                if !player.alive || player.energy >= ACTION_ENERGY {
+                    break;
+                }
+            }
+            self.tick();
+        }
+        self.remove_dead();
+        self.update_fov();
+    }
+
+    /// Whether the player has died.
+    pub fn is_over(&self) -> bool {
+        !self.get_player().alive
+    }
+
+    pub fn statistics(&self) -> &Statistics {
+        &self.stats
+    }
+
+    pub fn turn(&self) -> u32 {
+        self.turn
+    }
+
+    pub fn depth(&self) -> i32 {
+        self.depth
+    }
+
+    /// Leaves the belongings of dead characters on the ground and removes
+    /// the characters from the game. The player is kept even when dead.
+    fn remove_dead(&mut self) {
+        let mut i = 1;
+        while i < self.objects.len() {
+            if self.get_object(i).alive {
+                i += 1;
                 continue;
             }
-            o.update(self);
-            let action = o.next_action(self);
-            let o_pos = o.pos;
-            // Ask the controller for the next action
-            match action {
-                Action::Idle      => {}
-                Action::Move(pos) => {
-                    let new_pos = pos.to_pos() + o_pos;
-                    if self.is_walkable(new_pos) {
-                        // Walk if there is nothing in the way
-                        o.pos = new_pos;
-                    } else {
-                        // Check whether the thing in the way was another object.
-                        // If yes, then attack.
-                        for j in 0..self.objects.len() {
-                            if i == j {
-                                // We don't want to attack ourselves
-                                continue;
-                            }
-                            let ref mut other = self.objects[j].borrow_mut();
-                            if other.pos == new_pos {
-                                let msg = o.attack(other);
-                                // Append an attack message
-                                self.messages.push(msg);
-                                if !other.alive {
-                                    let msg = Message::new(format!("{} dies!", other.name));
-                                    self.messages.push(msg);
-                                }
-                            }
-                        }
-                    }
+            let o = self.objects.remove(i).into_inner();
+            for item in o.inventory.items {
+                self.drop_item_at(o.pos, item);
+            }
+        }
+    }
+
+    /// Advances the game time by one tick. Every living object gains energy
+    /// according to its speed and NPCs spend it on actions.
+    fn tick(&mut self) {
+        self.turn += 1;
+        self.messages.set_turn(self.turn);
+        // Noises from the previous tick can still be heard by the objects
+        // that acted before the noise was made
+        let turn = self.turn;
+        self.noises.retain(|n| n.turn + 1 >= turn);
+
+        for i in 0..self.objects.len() {
+            {
+                let ref mut o = self.objects[i].borrow_mut();
+                if !o.alive {
+                    continue;
                 }
-                Action::GetItem(item_i) => {
-                    for j in 0..self.objects.len() {
-                        if i == j || o.pos != self.objects[j].borrow().pos {
-                            // No point getting stuff from our own inventory
-                            continue;
-                        }
-                        let ref mut other = self.objects[j].borrow_mut();
-                        let item = other.inventory.remove_item(item_i);
-                        o.inventory.add_item(item);
+                let msgs = o.tick_statuses();
+                if i == 0 || self.is_visible(o.pos) {
+                    for msg in msgs {
+                        self.messages.push(Message::flavor(msg));
                     }
                 }
-                Action::DropItem(item_i) => {
-                    for j in 0..self.objects.len() {
-                        if i == j || o.pos != self.objects[j].borrow().pos {
-                            continue;
-                        }
-                        let ref mut other = self.objects[j].borrow_mut();
-                        if !other.alive {
-                            let item = o.inventory.remove_item(item_i);
-                            other.inventory.add_item(item);
-                        } else {
-                            panic!("Attempting to take stuff from a living being!");
-                        }
+                if !o.alive {
+                    self.messages.push(Message::death(o.name));
+                    if i == 0 {
+                        self.stats.cause_of_death = Some("Succumbed to wounds".to_string());
                     }
+                    continue;
                 }
+                if !o.has_status(StatusKind::Stunned) {
+                    o.energy += o.speed();
+                }
+            }
+            // The player spends energy only when there is input
+            if i == 0 {
+                continue;
+            }
+            while self.get_object(i).alive && self.get_object(i).energy >= ACTION_ENERGY {
+                self.act(i);
             }
         }
+    }
 
+    /// Asks the object at index `i` for its next action and carries it out.
+    fn act(&mut self, i: usize) {
+        let action = {
+            let ref mut o = self.objects[i].borrow_mut();
+            // Do nothing if object is dead
+            if !o.alive {
+                return;
+            }
+            o.update(self);
+            let action = o.next_action(self);
+            o.energy -= o.action_cost(action);
+            action
+        };
+        self.perform_action(i, action);
+    }
+
+    /// Updates the field of view and the fog of war around the player.
+    fn update_fov(&mut self) {
         // Update FOV
-        let player_pos = self.get_player().pos;
+        let (player_pos, sight_radius) = {
+            let player = self.get_player();
+            (player.pos, player.sight())
+        };
         self.map.tcod_map.compute_fov(
             player_pos.x, 
             player_pos.y, 
-            32, 
+            sight_radius, 
             true, 
             FovAlgorithm::Diamond);
 
@@ -212,9 +345,441 @@ impl GameState {
         }
     }
 
+    /// Carries out an action of the object at index `i`.
+    fn perform_action(&mut self, i: usize, action: Action) {
+        match action {
+            Action::Idle                => {}
+            Action::Move(dir)           => self.move_object(i, dir),
+            Action::GetItem(id)         => self.get_item(i, id),
+            Action::DropItem(id, count) => self.drop_item(i, id, count),
+            Action::UseItem(id)         => {
+                let pos = self.get_object(i).pos;
+                self.use_item(i, id, pos);
+            }
+            Action::UseItemOn(id, target) => {
+                let pos = self.get_object(i).pos;
+                // The effect lands where a thrown item would
+                let end = *self.projectile_path(pos, target, THROW_RANGE)
+                    .last()
+                    .unwrap_or(&pos);
+                self.use_item(i, id, end);
+            }
+            Action::Shoot(target)       => self.shoot(i, target),
+            Action::Throw(id, target)   => self.throw(i, id, target),
+            Action::Equip(id)           => self.equip(i, id),
+            Action::Unequip(id)         => self.unequip(i, id),
+            Action::PutItem(container, id, count) => self.put_item(i, container, id, count),
+            Action::TakeItem(container, id) => self.take_item(i, container, id),
+        }
+    }
+
+    /// Moves the object at index `i` one step in direction `dir`, attacking
+    /// whatever is in the way.
+    fn move_object(&mut self, i: usize, dir: Dir) {
+        let ref mut o = self.objects[i].borrow_mut();
+        let new_pos = dir.to_pos() + o.pos;
+        if self.is_walkable(new_pos) && o.is_overloaded() {
+            if i == 0 {
+                let msg = format!("{} is carrying too much to move.", o.name);
+                self.messages.push(Message::warning(msg));
+            }
+        } else if self.is_walkable(new_pos) {
+            // Walk if there is nothing in the way
+            o.pos = new_pos;
+            let volume = if o.sneaking {
+                SNEAK_FOOTSTEP_VOLUME
+            } else {
+                FOOTSTEP_VOLUME
+            };
+            self.noises.push(Noise::new(new_pos, volume, o.faction, self.turn));
+        } else {
+            // Check whether the thing in the way was another object.
+            // If yes, then attack.
+            for j in 0..self.objects.len() {
+                if i == j {
+                    // We don't want to attack ourselves
+                    continue;
+                }
+                let ref mut other = self.objects[j].borrow_mut();
+                if other.pos == new_pos {
+                    let health = other.health;
+                    let msg = o.attack(other);
+                    if i == 0 {
+                        self.stats.damage_dealt += health - other.health;
+                    }
+                    // Append an attack message
+                    self.messages.push(msg);
+                    self.noises.push(
+                        Noise::new(new_pos, COMBAT_VOLUME, o.faction, self.turn));
+                    if !other.alive {
+                        self.messages.push(Message::death(other.name));
+                        self.stats.record_kill(i, j, format!("Killed by {}", o.name));
+                    }
+                }
+            }
+        }
+    }
+
+    /// Tells the player that an item they wanted to do something with no
+    /// longer exists. Other objects fail silently.
+    fn item_missing(&mut self, i: usize) {
+        if i == 0 {
+            self.add_message(Message::warning("That item is no longer there.".to_string()));
+        }
+    }
+
+    /// Picks up the item with id `id` from the ground under the object at
+    /// index `i`.
+    fn get_item(&mut self, i: usize, id: ItemId) {
+        let pos = self.get_object(i).pos;
+        let item = match self.take_item_at(pos, id) {
+            Some(item) => item,
+            None       => return self.item_missing(i),
+        };
+        let ref mut o = self.objects[i].borrow_mut();
+        if item.fixed {
+            let msg = format!("{} can't pick up {}.", o.name, item.name);
+            self.messages.push(Message::warning(msg));
+            self.ground.entry(pos).or_insert_with(Inventory::new).add_item(item);
+            return;
+        }
+        if !o.can_carry(&item) {
+            let msg = format!("{} can't carry {}.", o.name, item.display_name());
+            self.messages.push(Message::warning(msg));
+            self.ground.entry(pos).or_insert_with(Inventory::new).add_item(item);
+            return;
+        }
+        o.inventory.add_item(item);
+    }
+
+    /// Drops `count` items from the stack with id `id` onto the tile of the
+    /// object at index `i`.
+    fn drop_item(&mut self, i: usize, id: ItemId, count: i32) {
+        let (pos, item) = {
+            let ref mut o = self.get_object_mut(i);
+            (o.pos, o.inventory.split_item(id, count))
+        };
+        match item {
+            Some(item) => self.drop_item_at(pos, item),
+            None       => self.item_missing(i),
+        }
+    }
+
+    /// Runs `f` on the container with id `id`, which is either carried by
+    /// the object at index `i` or lies on the ground under it. Returns `None`
+    /// if there is no such container.
+    fn with_container<R>(
+        &mut self,
+        i: usize,
+        id: ItemId,
+        f: impl FnOnce(&mut Inventory) -> R) -> Option<R> {
+
+        let pos = {
+            let ref mut o = self.get_object_mut(i);
+            let carried = o.inventory.items
+                .iter_mut()
+                .find(|x| x.id == id)
+                .and_then(|x| x.contents.as_mut());
+            if let Some(contents) = carried {
+                return Some(f(contents));
+            }
+            o.pos
+        };
+        self.ground
+            .get_mut(&pos)?
+            .items
+            .iter_mut()
+            .find(|x| x.id == id)
+            .and_then(|x| x.contents.as_mut())
+            .map(f)
+    }
+
+    /// Puts `count` items from the stack with id `id` into the container
+    /// with id `container`.
+    fn put_item(&mut self, i: usize, container: ItemId, id: ItemId, count: i32) {
+        if container == id {
+            if i == 0 {
+                let msg = "You can't put something inside itself.".to_string();
+                self.messages.push(Message::warning(msg));
+            }
+            return;
+        }
+        let item = self.get_object_mut(i).inventory.split_item(id, count);
+        let item = match item {
+            Some(item) => item,
+            None       => return self.item_missing(i),
+        };
+        // The item goes back to the inventory if the container is gone
+        let mut item = Some(item);
+        self.with_container(i, container, |x| x.add_item(item.take().unwrap()));
+        if let Some(item) = item {
+            self.get_object_mut(i).inventory.add_item(item);
+            self.item_missing(i);
+        }
+    }
+
+    /// Takes the item with id `id` out of the container with id `container`.
+    fn take_item(&mut self, i: usize, container: ItemId, id: ItemId) {
+        let item = self.with_container(i, container, |x| x.remove_item(id));
+        let item = match item.flatten() {
+            Some(item) => item,
+            None       => return self.item_missing(i),
+        };
+        // Items taken out of a carried bag were being carried already
+        let carried = self.get_object(i).inventory.get_item(container).is_some();
+        let can_carry = carried || self.get_object(i).can_carry(&item);
+        if !can_carry {
+            let msg = {
+                let o = self.get_object(i);
+                format!("{} can't carry {}.", o.name, item.display_name())
+            };
+            self.messages.push(Message::warning(msg));
+            self.with_container(i, container, |x| x.add_item(item));
+            return;
+        }
+        self.get_object_mut(i).inventory.add_item(item);
+    }
+
+    /// Tries to open the container with id `id` for the player, unlocking it
+    /// if the player has the right key. Returns whether the container could
+    /// be opened.
+    pub fn open_container(&mut self, id: ItemId) -> bool {
+        let pos = self.get_player().pos;
+        let (name, lock) = {
+            let player = self.get_player();
+            let container = player.inventory
+                .get_item(id)
+                .or(self.items_at(pos).and_then(|x| x.get_item(id)));
+            match container {
+                Some(x) => (x.name.clone(), x.lock.clone()),
+                None    => return false,
+            }
+        };
+        let lock = match lock {
+            Some(lock) => lock,
+            None       => return true,
+        };
+        if self.get_player().inventory.find(&lock).is_none() {
+            self.add_message(Message::warning(format!("The {} is locked.", name)));
+            return false;
+        }
+        let msg = format!("You unlock the {} with the {}.", name, lock);
+        self.add_message(Message::loot(msg));
+        if let Some(pile) = self.ground.get_mut(&pos) {
+            pile.items.iter_mut().filter(|x| x.id == id).for_each(|x| x.lock = None);
+        }
+        self.get_player_mut()
+            .inventory
+            .items
+            .iter_mut()
+            .filter(|x| x.id == id)
+            .for_each(|x| x.lock = None);
+        true
+    }
+
+    /// Copy of the items inside the container with id `id`, which is either
+    /// carried by the player or lies on the ground under the player.
+    pub fn container_contents(&self, id: ItemId) -> Option<Inventory> {
+        let player = self.get_player();
+        player.inventory
+            .get_item(id)
+            .or(self.items_at(player.pos).and_then(|x| x.get_item(id)))
+            .and_then(|x| x.contents.clone())
+    }
+
+    /// Finds the item with id `id` among the player's belongings and
+    /// everything lying on the ground.
+    pub fn find_item(&self, id: ItemId) -> Option<Item> {
+        let player = self.get_player();
+        let item = player.inventory
+            .find_nested(id)
+            .or(player.equipment.items.iter().find(|x| x.id == id))
+            .or_else(|| self.ground.values().find_map(|x| x.find_nested(id)));
+        item.cloned()
+    }
+
+    /// Finds a container lying on the ground at position `pos`.
+    pub fn container_at(&self, pos: Pos) -> Option<ItemId> {
+        self.items_at(pos)?
+            .items
+            .iter()
+            .find(|x| x.contents.is_some())
+            .map(|x| x.id)
+    }
+
+    fn equip(&mut self, i: usize, id: ItemId) {
+        let wearable = self.get_object(i)
+            .inventory
+            .get_item(id)
+            .map(|item| !item.wearable.is_empty());
+        let wearable = match wearable {
+            Some(wearable) => wearable,
+            None           => return self.item_missing(i),
+        };
+        let ref mut o = self.objects[i].borrow_mut();
+        if !wearable {
+            let msg = format!("{} can't be worn.", o.inventory.get_item(id).unwrap().name);
+            self.messages.push(Message::warning(msg));
+            return;
+        }
+        let item = o.inventory.split_item(id, 1).expect("Item disappeared");
+        let msg = format!("{} puts on {}.", o.name, item.name);
+        self.messages.push(Message::loot(msg));
+        for removed in o.equipment.equip(*item) {
+            let msg = format!("{} takes off {}.", o.name, removed.name);
+            self.messages.push(Message::loot(msg));
+            o.inventory.add_item(Box::new(removed));
+        }
+        // Taking off a constitution bonus lowers maximum health
+        o.health = min(o.health, o.max_health());
+    }
+
+    fn unequip(&mut self, i: usize, id: ItemId) {
+        let item = self.get_object_mut(i).equipment.unequip(id);
+        let item = match item {
+            Some(item) => item,
+            None       => return self.item_missing(i),
+        };
+        let ref mut o = self.objects[i].borrow_mut();
+        let msg = format!("{} takes off {}.", o.name, item.name);
+        self.messages.push(Message::loot(msg));
+        o.inventory.add_item(Box::new(item));
+        o.health = min(o.health, o.max_health());
+    }
+
+    /// Noises made during the current and the previous turn.
+    pub fn noises(&self) -> &Vec<Noise> {
+        &self.noises
+    }
+
+    /// Shoots the firearm wielded by the object at index `i` towards position
+    /// `target`.
+    fn shoot(&mut self, i: usize, target: Pos) {
+        let (pos, name, faction, weapon) = {
+            let o = self.get_object(i);
+            match o.equipment.firearm() {
+                Some(w) => (o.pos, o.name, o.faction, w.clone()),
+                None    => return,
+            }
+        };
+        let ammo = weapon.ranged
+            .as_ref()
+            .and_then(|r| r.ammo.clone())
+            .expect("Firearms need ammunition");
+        let ammo_i = self.get_object(i).inventory.find(&ammo);
+        match ammo_i {
+            Some(ammo_i) => {
+                self.get_object_mut(i).inventory.split_item(ammo_i, 1);
+            }
+            None => {
+                let msg = format!("{} is out of {}.", name, ammo);
+                self.messages.push(Message::warning(msg));
+                return;
+            }
+        }
+        self.noises.push(Noise::new(pos, GUNSHOT_VOLUME, faction, self.turn));
+        self.launch(i, &weapon, target);
+    }
+
+    /// Throws one item of the stack with id `id` of the object at index `i` towards
+    /// position `target`. The item lands at the end of its flight.
+    fn throw(&mut self, i: usize, id: ItemId, target: Pos) {
+        let (pos, faction, item) = {
+            let ref mut o = self.get_object_mut(i);
+            (o.pos, o.faction, o.inventory.split_item(id, 1))
+        };
+        let item = match item {
+            Some(item) => item,
+            None       => return self.item_missing(i),
+        };
+        self.noises.push(Noise::new(pos, COMBAT_VOLUME, faction, self.turn));
+        let end = self.launch(i, &item, target);
+        self.drop_item_at(end, item);
+    }
+
+    /// Sends `item` flying from the object at index `i` towards position
+    /// `target` and hits whoever is standing at the end of the path. Returns
+    /// the position where the flight ended.
+    fn launch(&mut self, i: usize, item: &Item, target: Pos) -> Pos {
+        let (pos, name) = {
+            let o = self.get_object(i);
+            (o.pos, o.name)
+        };
+        let range = item.ranged.as_ref().map_or(THROW_RANGE, |r| r.range);
+        let path = self.projectile_path(pos, target, range);
+        let end = match path.last() {
+            Some(p) => *p,
+            None    => pos,
+        };
+        let victim = self.objects_at_unsafe(end)
+            .into_iter()
+            .find(|j| *j != i && self.get_object(*j).blocking);
+        if let Some(j) = victim {
+            let health = self.get_object(j).health;
+            let msg = self.get_object(i).ranged_attack(&mut self.objects[j].borrow_mut(), item);
+            self.messages.push(msg);
+            let (alive, other_name, damage) = {
+                let other = self.get_object(j);
+                (other.alive, other.name, health - other.health)
+            };
+            if i == 0 {
+                self.stats.damage_dealt += damage;
+            }
+            if !alive {
+                self.messages.push(Message::death(other_name));
+                self.stats.record_kill(i, j, format!("Killed by {}'s {}", name, item.name));
+            }
+        } else {
+            let msg = format!("{}'s {} hits nothing.", name, item.name);
+            self.messages.push(Message::combat(msg));
+        }
+        end
+    }
+
+    /// Traces the flight of a projectile from `from` towards `to`. The path
+    /// ends at the first blocking character, before the first wall or after
+    /// `range` tiles.
+    pub fn projectile_path(&self, from: Pos, to: Pos, range: i32) -> Vec<Pos> {
+        let mut path = vec![];
+        for (x, y) in Line::new(from.tup(), to.tup()).take(range as usize) {
+            let pos = Pos::new(x, y);
+            if self.is_solid(pos) {
+                break;
+            }
+            path.push(pos);
+            if !self.is_walkable(pos) {
+                break;
+            }
+        }
+        path
+    }
+
+    /// Puts an item on the ground at position `pos`.
+    pub fn drop_item_at(&mut self, pos: Pos, item: Box<Item>) {
+        self.ground
+            .entry(pos)
+            .or_insert_with(Inventory::new)
+            .add_item(item);
+    }
+
+    /// Items lying on the ground at position `pos`.
+    pub fn items_at(&self, pos: Pos) -> Option<&Inventory> {
+        self.ground.get(&pos).filter(|x| !x.items.is_empty())
+    }
+
+    /// Removes the item with id `id` from the ground at position `pos`.
+    fn take_item_at(&mut self, pos: Pos, id: ItemId) -> Option<Box<Item>> {
+        let pile = self.ground.get_mut(&pos)?;
+        let item = pile.remove_item(id);
+        if pile.items.is_empty() {
+            self.ground.remove(&pos);
+        }
+        item
+    }
+
     /// Checks whether the tile at position `pos` is currently visible to
     /// the player.
-    fn is_visible(&self, pos: Pos) -> bool {
+    pub fn is_visible(&self, pos: Pos) -> bool {
         let in_bounds = 
             pos.x >=0 &&
             pos.y >=0 &&
@@ -227,18 +792,55 @@ impl GameState {
             }
     }
 
-    fn cam_pos(&self) -> Pos {
+    /// Describes what the player knows about the tile at position `pos`:
+    /// the tile itself and, if it is in view, the characters and items on
+    /// it.
+    pub fn describe_tile(&self, pos: Pos) -> Vec<String> {
+        let tile = self.map.get_tile(pos);
+        if !self.is_visible(pos) {
+            return if tile.explored {
+                vec![format!("You remember a {} here.", tile.name)]
+            } else {
+                vec!["You don't know what is there.".to_string()]
+            };
+        }
+        let mut lines = vec![format!("You see a {}.", tile.name)];
+        for o in self.objects.iter().map(|x| x.borrow()).filter(|x| x.pos == pos) {
+            let mut states = vec![o.condition()];
+            states.extend(o.statuses.iter().map(|x| x.kind.name()));
+            if o.sneaking {
+                states.push("sneaking");
+            }
+            let name = if o.faction == Faction::Player { "You" } else { o.name };
+            lines.push(format!("{} ({})", name, states.join(", ")));
+        }
+        if let Some(pile) = self.items_at(pos) {
+            let names: Vec<String> = pile.items.iter().rev().map(|x| x.display_name()).collect();
+            lines.push(format!("Lying here: {}", names.join(", ")));
+        }
+        lines
+    }
+
+    /// Living characters hostile to the player that the player can see.
+    pub fn visible_enemies(&self) -> Vec<Ref<Character>> {
+        self.objects[1..]
+            .iter()
+            .map(|o| o.borrow())
+            .filter(|o| {
+                o.alive && o.faction.is_hostile(Faction::Player) && self.is_visible(o.pos)
+            })
+            .collect()
+    }
+
+    /// Position of the top left corner of the viewport on the map.
+    pub fn cam_pos(&self) -> Pos {
         self.get_player().pos - Pos::new(VIEWPORT_WIDTH/2, VIEWPORT_HEIGHT/2)
     }
 
     fn render_object(&self, con: &mut dyn Console, o: &Character) {
-        if !o.alive && o.inventory.items.len() == 0 {
-            return;
-        }
         if self.is_visible(o.pos){
             let view_pos = o.pos - self.cam_pos();
-            if view_pos.x >= 0 && view_pos.x < VIEWPORT_WIDTH &&
-               view_pos.y >= 0 && view_pos.y < VIEWPORT_HEIGHT {
+            if VIEWPORT.contains(view_pos) {
                 o.draw(view_pos, con);
             }
         }
@@ -246,14 +848,16 @@ impl GameState {
 
     /// Renders the whole screen
     pub fn render(&self, con: &mut Offscreen) {
+        con.set_default_foreground(WHITE);
+        con.set_default_background(BLACK);
+        con.clear();
         self.render_viewport(con);
-        self.render_gui(con);
+        self.render_sidebar(con);
+        self.render_log(con);
     }
 
     /// Renders all tiles and game objects on the screen.
     fn render_viewport(&self, con: &mut dyn Console) {
-        con.set_default_foreground(WHITE);
-        con.clear();
         for sx in 0..VIEWPORT_WIDTH {
             for sy in 0..VIEWPORT_HEIGHT {
                 let pos = Pos::new(sx, sy);
@@ -268,6 +872,15 @@ impl GameState {
                 }
             }
         }
+        // Draw the topmost item of every visible pile
+        for (pos, pile) in self.ground.iter() {
+            let view_pos = *pos - self.cam_pos();
+            if let Some(top) = pile.items.last() {
+                if VIEWPORT.contains(view_pos) && self.is_visible(*pos) {
+                    top.draw(view_pos, con);
+                }
+            }
+        }
         // Draw non-blocking objects
         for i in 0..self.objects.len() {
             let ref o = self.get_object(i);
@@ -284,34 +897,154 @@ impl GameState {
         }
     }
 
-    fn render_gui(&self, con: &mut Offscreen) {
-        let idx = max(self.messages.len() as i32 - MSG_DISPLAY_COUNT, 0);
-        let tail = &self.messages[idx as usize..];
-        for i in 0..tail.len() {
-            con.set_default_foreground(tail[i].color);
-            con.print(
-                0, 
-                VIEWPORT_HEIGHT - MSG_DISPLAY_COUNT + i as i32, 
-                &tail[i].text);
-        }
-        let player = self.get_player();
-        let player_health_ratio: f32 = player.health as f32 / player.max_health() as f32;
-        con.set_default_foreground(WHITE);
-        con.print(0, VIEWPORT_HEIGHT - MSG_DISPLAY_COUNT - 2, "HP");
-        for i in 0..HEALTH_BAR_WIDTH {
-            let color = if player_health_ratio <= i as f32 / HEALTH_BAR_WIDTH as f32 {
+    /// Draws a horizontal bar that is filled according to `ratio`.
+    fn render_bar(&self, con: &mut Offscreen, pos: Pos, width: i32, ratio: f32) {
+        for i in 0..width {
+            let color = if ratio <= i as f32 / width as f32 {
                 HEALTH_BAR_BG_COLOR
             } else {
                 HEALTH_BAR_FG_COLOR
             };
-            con.set_default_background(color);
-            con.put_char(
-                i+3,
-                VIEWPORT_HEIGHT - MSG_DISPLAY_COUNT - 2,
-                ' ',
-                BackgroundFlag::Set)
+            con.set_char_background(pos.x + i, pos.y, color, BackgroundFlag::Set);
+        }
+    }
+
+    /// Renders the player's stats, equipment and statuses next to the
+    /// viewport, followed by the enemies in sight.
+    fn render_sidebar(&self, con: &mut Offscreen) {
+        let x = SIDEBAR.x + 2;
+        let width = SIDEBAR.w - 3;
+        let mut y = SIDEBAR.y + 1;
+        con.set_default_foreground(DARK_GREY);
+        for sy in SIDEBAR.y..SIDEBAR.y + SIDEBAR.h {
+            con.put_char(SIDEBAR.x, sy, '|', BackgroundFlag::None);
+        }
+        let player = self.get_player();
+        let stats = player.stats();
+        con.set_default_foreground(YELLOW);
+        con.print(x, y, player.name);
+        y += 2;
+
+        con.set_default_foreground(WHITE);
+        con.print(x, y, "HP");
+        let ratio = player.health as f32 / player.max_health() as f32;
+        self.render_bar(con, Pos::new(x + 3, y), HEALTH_BAR_WIDTH, ratio);
+        con.print_ex(
+            x + 3 + HEALTH_BAR_WIDTH / 2,
+            y,
+            BackgroundFlag::None,
+            TextAlignment::Center,
+            format!("{}/{}", player.health, player.max_health()));
+        y += 1;
+        con.print(x, y, format!("Str {}  Agi {}  Con {}", stats.str, stats.agi, stats.con));
+        y += 1;
+        con.print(x, y, format!("Depth {}  Turn {}", self.depth, self.turn));
+        y += 2;
+
+        con.set_default_foreground(LIGHT_BLUE);
+        con.print(x, y, "Equipment");
+        y += 1;
+        con.set_default_foreground(WHITE);
+        if player.equipment.items.is_empty() {
+            con.print(x + 1, y, "(nothing)");
+            y += 1;
+        }
+        for item in player.equipment.items.iter() {
+            let name: String = item.name.chars().take((width - 1) as usize).collect();
+            con.print(x + 1, y, name);
+            y += 1;
+        }
+        y += 1;
+
+        con.set_default_foreground(LIGHT_BLUE);
+        con.print(x, y, "Effects");
+        y += 1;
+        con.set_default_foreground(WHITE);
+        if player.statuses.is_empty() && !player.sneaking {
+            con.print(x + 1, y, "(none)");
+            y += 1;
+        }
+        for status in player.statuses.iter() {
+            let (icon, color) = status.kind.icon();
+            con.set_default_foreground(color);
+            con.put_char(x + 1, y, icon, BackgroundFlag::None);
+            con.set_default_foreground(WHITE);
+            con.print(x + 3, y, format!("{} ({})", status.kind.name(), status.turns));
+            y += 1;
+        }
+        if player.sneaking {
+            con.print(x + 3, y, "Sneaking");
+            y += 1;
+        }
+        y += 1;
+
+        con.set_default_foreground(LIGHT_BLUE);
+        con.print(x, y, "Enemies in sight");
+        y += 1;
+        let enemies = self.visible_enemies();
+        con.set_default_foreground(WHITE);
+        if enemies.is_empty() {
+            con.print(x + 1, y, "(none)");
+        }
+        for enemy in enemies.iter() {
+            if y >= SIDEBAR.y + SIDEBAR.h - HOVER_HEIGHT - 1 {
+                break;
+            }
+            con.set_default_foreground(enemy.color);
+            con.put_char(x + 1, y, enemy.char, BackgroundFlag::None);
+            con.set_default_foreground(WHITE);
+            let name: String = enemy.name.chars().take((width - ENEMY_BAR_WIDTH - 5) as usize).collect();
+            con.print(x + 3, y, name);
+            let ratio = enemy.health as f32 / enemy.max_health() as f32;
+            self.render_bar(con, Pos::new(x + width - ENEMY_BAR_WIDTH, y), ENEMY_BAR_WIDTH, ratio);
+            y += 1;
         }
-        con.set_default_background(DEFAULT_BACKGROUND_COLOR);
+        con.set_default_foreground(WHITE);
+    }
+
+    /// Highlights the tile at the screen position `screen_pos` and
+    /// describes it at the bottom of the sidebar.
+    pub fn render_hover(&self, con: &mut Offscreen, screen_pos: Pos) {
+        if !VIEWPORT.contains(screen_pos) {
+            return;
+        }
+        con.set_char_background(screen_pos.x, screen_pos.y, DARK_YELLOW, BackgroundFlag::Set);
+        let x = SIDEBAR.x + 2;
+        let y = SIDEBAR.y + SIDEBAR.h - HOVER_HEIGHT;
+        con.set_default_foreground(LIGHT_BLUE);
+        con.print(x, y, "Under the mouse");
+        con.set_default_foreground(WHITE);
+        let lines = self
+            .describe_tile(screen_pos + self.cam_pos())
+            .iter()
+            .flat_map(|x| wrap_text(x, SIDEBAR.w - 4))
+            .take((HOVER_HEIGHT - 2) as usize)
+            .collect::<Vec<String>>();
+        for (i, line) in lines.iter().enumerate() {
+            con.print(x + 1, y + 1 + i as i32, line);
+        }
+    }
+
+    /// Renders the most recent messages below the viewport, newest at the
+    /// bottom. Long messages are wrapped over several lines.
+    fn render_log(&self, con: &mut Offscreen) {
+        con.set_default_foreground(DARK_GREY);
+        for sx in LOG.x..LOG.x + LOG.w {
+            con.put_char(sx, LOG.y, '-', BackgroundFlag::None);
+        }
+        let mut y = LOG.y + LOG.h;
+        for msg in self.messages.messages().iter().rev() {
+            let lines = wrap_text(&msg.display_text(), LOG.w - 2);
+            if y - (lines.len() as i32) <= LOG.y {
+                break;
+            }
+            y -= lines.len() as i32;
+            con.set_default_foreground(msg.severity.color());
+            for (i, line) in lines.iter().enumerate() {
+                con.print(LOG.x + 1, y + i as i32, line);
+            }
+        }
+        con.set_default_foreground(WHITE);
     }
 
     /// Get a mutable reference to the player object.
@@ -332,6 +1065,18 @@ impl GameState {
         self.objects[i].borrow_mut()
     }
 
+    pub fn add_message(&mut self, msg: Message) {
+        self.messages.push(msg);
+    }
+
+    pub fn set_message_cap(&mut self, cap: usize) {
+        self.messages.set_cap(cap);
+    }
+
+    pub fn messages(&self) -> &MessageLog {
+        &self.messages
+    }
+
     pub fn player_action(&mut self, a: Action) {
         match *self.get_player_mut().controller {
             Controller::PlayerController{ref mut action} => *action = a,
@@ -340,6 +1085,28 @@ impl GameState {
         self.update();
     }
 
+    /// Get references to all objects except the one that is currently being
+    /// updated.
+    pub fn others(&self) -> Vec<Ref<Character>> {
+        self.objects
+            .iter()
+            .filter_map(|o| o.try_borrow().ok())
+            .collect()
+    }
+
+    pub fn is_solid(&self, pos: Pos) -> bool {
+        self.map.is_solid(pos)
+    }
+
+    /// Whether the player has seen the tile at `pos`.
+    pub fn is_explored(&self, pos: Pos) -> bool {
+        self.map.get_tile(pos).explored
+    }
+
+    pub fn is_opaque(&self, pos: Pos) -> bool {
+        self.map.get_tile(pos).opaque
+    }
+
     pub fn is_walkable(&self, pos: Pos) -> bool {
         let blocking_object = self
             .objects_at_unsafe(pos)
@@ -348,21 +1115,6 @@ impl GameState {
         !self.map.is_solid(pos) && !blocking_object
     }
 
-    pub fn objects_at(&self, pos: Pos) -> Vec<i32> {
-        let mut indices = vec![];
-        for i in 0..self.objects.len() {
-            match self.objects[i].try_borrow() {
-                Ok(r) => {
-                    if r.pos == pos && r.inventory.items.len() > 0 {
-                        indices.push(i as i32);
-                    }
-                }
-                Err(_) => panic!("Could not borrow object"),
-            }
-        }
-        return indices;
-    }
-
     fn objects_at_unsafe(&self, pos: Pos) -> Vec<usize> {
         let mut indices = vec![];
         let mut fails = 0;
@@ -379,40 +1131,216 @@ impl GameState {
         return indices;
     }
 
+    /// Checks whether there is nothing opaque between positions `a` and `b`.
     pub fn check_los(&self, a: Pos, b: Pos) -> bool {
         let mut line = Line::new(a.tup(), b.tup());
-        line.all(|(x, y)| self.is_visible(Pos::new(x, y)))
+        line.all(|(x, y)| {
+            let pos = Pos::new(x, y);
+            pos == b || !self.is_opaque(pos)
+        })
+    }
+
+    /// Checks whether `viewer` can see `target` with its own eyes. Sneaking
+    /// characters can only be noticed from half the usual distance.
+    pub fn can_see(&self, viewer: &Character, target: &Character) -> bool {
+        let radius = if target.sneaking {
+            viewer.sight() / 2
+        } else {
+            viewer.sight()
+        };
+        viewer.pos.distance(target.pos) <= radius && self.check_los(viewer.pos, target.pos)
     }
 
-    pub fn apply_effect(&mut self, idx: i32, effect: &ItemEffect) {
+    /// Toggles sneaking for the player. Sneaking characters make less noise
+    /// and are harder to spot.
+    pub fn toggle_sneak(&mut self) {
+        let sneaking = {
+            let ref mut player = self.get_player_mut();
+            player.sneaking = !player.sneaking;
+            player.sneaking
+        };
+        let msg = if sneaking {
+            "You start sneaking."
+        } else {
+            "You stop sneaking."
+        };
+        self.messages.push(Message::flavor(msg.to_string()));
+    }
+
+    /// Builds a pathfinder over the map. Other characters can be walked
+    /// through, but are avoided if possible.
+    fn pathfinder(&self) -> AStar {
+        AStar::new_from_callback(
+            MAP_SIZE,
+            MAP_SIZE,
+            move |_from, (x, y)| {
+                let pos = Pos::new(x, y);
+                if self.is_solid(pos) {
+                    0.0
+                } else if self.is_walkable(pos) {
+                    1.0
+                } else {
+                    5.0
+                }
+            },
+            0.0)
+    }
+
+    /// Finds the direction of the first step on the shortest path from `from`
+    /// to `to`.
+    pub fn path_step(&self, from: Pos, to: Pos) -> Option<Dir> {
+        let mut path = self.pathfinder();
+        if !path.find(from.tup(), to.tup()) {
+            return None;
+        }
+        let (x, y) = path.walk_one_step(false)?;
+        (Pos::new(x, y) - from).to_dir()
+    }
+
+    /// Finds the index of the living character standing at position `pos`.
+    pub fn character_at(&self, pos: Pos) -> Option<usize> {
+        self.objects_at_unsafe(pos)
+            .into_iter()
+            .find(|i| self.get_object(*i).alive)
+    }
+
+    /// Applies an item effect of the object at index `user` at position
+    /// `target`. Effects on characters affect whoever is standing there.
+    /// `source` names the item, e.g. "Raider's molotov".
+    pub fn apply_effect(&mut self, user: usize, source: &str, target: Pos, effect: &ItemEffect) {
+        let victim = self.character_at(target);
         match effect {
-            ItemEffect::Message(msg) => self.messages.push(Message::new(msg.to_string())),
+            ItemEffect::Message(msg) => self.messages.push(Message::flavor(msg.to_string())),
             ItemEffect::ChangeHealth(amt) => {
-                let ref mut obj = self.get_object_mut(idx as usize);
-                if *amt <= 0 {
-                    obj.take_damage(*amt);
-                } else {
-                    obj.heal(*amt);
+                if let Some(j) = victim {
+                    let ref mut obj = self.objects[j].borrow_mut();
+                    if *amt < 0 {
+                        obj.take_damage(-*amt);
+                        if user == 0 && j != 0 {
+                            self.stats.damage_dealt += -*amt;
+                        }
+                        let msg = format!("{} takes {} damage.", obj.name, -*amt);
+                        if j == 0 {
+                            self.messages.push(Message::damage_taken(msg));
+                        } else {
+                            self.messages.push(Message::combat(msg));
+                        }
+                        if !obj.alive {
+                            self.messages.push(Message::death(obj.name));
+                            self.stats.record_kill(user, j, format!("Killed by {}", source));
+                        }
+                    } else {
+                        obj.heal(*amt);
+                        let msg = format!("{} feels better.", obj.name);
+                        self.messages.push(Message::flavor(msg).with_severity(Severity::Good));
+                    }
+                }
+            }
+            ItemEffect::ApplyStatus(kind, turns) => {
+                if let Some(j) = victim {
+                    let msg = self.objects[j].borrow_mut().add_status(*kind, *turns);
+                    if j == 0 {
+                        self.messages.push(Message::warning(msg));
+                    } else {
+                        self.messages.push(Message::combat(msg));
+                    }
+                }
+            }
+            ItemEffect::Teleport(range) => {
+                if let Some(j) = victim {
+                    let rng = Rng::get_instance();
+                    for _ in 0..100 {
+                        let pos = target + Pos::new(
+                            rng.get_int(-range, *range),
+                            rng.get_int(-range, *range));
+                        if self.is_walkable(pos) {
+                            let ref mut obj = self.objects[j].borrow_mut();
+                            obj.pos = pos;
+                            let msg = format!("{} vanishes and reappears nearby.", obj.name);
+                            self.messages.push(Message::flavor(msg));
+                            break;
+                        }
+                    }
+                }
+            }
+            ItemEffect::RevealMap(radius) => {
+                for x in target.x-radius..=target.x+radius {
+                    for y in target.y-radius..=target.y+radius {
+                        if let Some(tile) = self.map.get_tile_mut(Pos::new(x, y)) {
+                            tile.explored = true;
+                        }
+                    }
+                }
+                self.messages.push(Message::flavor("The surroundings are revealed.".to_string()));
+            }
+            ItemEffect::Area(radius, inner) => {
+                let targets: Vec<Pos> = self.others()
+                    .iter()
+                    .filter(|o| o.alive && o.pos.distance(target) <= *radius)
+                    .filter(|o| self.check_los(target, o.pos))
+                    .map(|o| o.pos)
+                    .collect();
+                for pos in targets {
+                    self.apply_effect(user, source, pos, inner);
                 }
             }
         }
     }
 
-    pub fn use_item(&mut self, obj_idx: i32, item_idx: i32) {
-        let (consumable, effects) = {
-            let obj = self.get_object(obj_idx as usize);
-            let item = obj.inventory.get_item(item_idx);
-            (item.consumable, item.on_use.clone())
+    /// Uses the item with id `id` of the object at index `i` on position
+    /// `target`.
+    pub fn use_item(&mut self, i: usize, id: ItemId, target: Pos) {
+        let found = {
+            let o = self.get_object(i);
+            o.inventory.get_item(id).map(|item| (
+                item.consumable,
+                item.on_use.clone(),
+                format!("{} uses {}.", o.name, item.name),
+                format!("{}'s {}", o.name, item.name)))
         };
-        for effect in effects {
-            self.apply_effect(obj_idx, &effect);
-        }
+        let (consumable, effects, msg, source) = match found {
+            Some(found) => found,
+            None        => return self.item_missing(i),
+        };
+        self.messages.push(Message::loot(msg));
         if consumable {
-            self.get_object_mut(obj_idx as usize).inventory.remove_item(item_idx);
+            self.get_object_mut(i).inventory.split_item(id, 1);
+        }
+        for effect in effects {
+            self.apply_effect(i, &source, target, &effect);
         }
     }
 }
 
+/// Creates a random item to be found lying around.
+fn random_loot() -> Item {
+    let loot = [
+        Item::shotgun,
+        Item::shotgun_shell,
+        Item::pistol_round,
+        Item::leather_jacket,
+        Item::helmet,
+        Item::crowbar,
+        Item::fire_axe,
+        Item::power_gloves,
+        Item::kevlar_pants,
+        Item::stim_pack,
+        Item::steroid_shot,
+        Item::poison_dart,
+        Item::grenade,
+        Item::flashbang,
+        Item::teleporter,
+        Item::map_chip,
+        Item::molotov,
+        Item::emp_grenade,
+        Item::backpack,
+        || Item::key("locker key"),
+        || Item::key("vending machine key"),
+    ];
+    let rng = Rng::get_instance();
+    loot[rng.get_int(0, loot.len() as i32 - 1) as usize]()
+}
+
 /// Generates a map using binary space partitioning. This will result in
 /// a map that resembles city streets.
 fn make_map() -> Map {
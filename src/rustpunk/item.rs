@@ -1,4 +1,30 @@
 
+use crate::rustpunk::combat::Dice;
+use crate::rustpunk::object::StatBlock;
+use crate::rustpunk::pos::Pos;
+use crate::rustpunk::status::StatusKind;
+
+use std::sync::atomic::AtomicU32;
+use std::sync::atomic::Ordering;
+
+use tcod::colors::*;
+use tcod::console::*;
+
+/// Counter used to hand out item ids.
+static NEXT_ITEM_ID: AtomicU32 = AtomicU32::new(1);
+
+/// Identifies a single item or stack of items for as long as it exists, no
+/// matter which inventory it is moved to.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct ItemId(u32);
+
+impl ItemId {
+    /// Hands out an id that no other item has.
+    pub fn next() -> Self {
+        ItemId(NEXT_ITEM_ID.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct Inventory {
     pub items: Vec<Box<Item>>,
@@ -11,16 +37,63 @@ impl Inventory {
         }
     }
 
+    /// Adds an item, merging it into a matching stack if there is one.
     pub fn add_item(&mut self, item: Box<Item>) {
-        self.items.push(item);
+        let stack = self.items
+            .iter_mut()
+            .find(|x| x.stacks_with(&item));
+        match stack {
+            Some(stack) => stack.count += item.count,
+            None        => self.items.push(item),
+        }
+    }
+
+    /// Removes the whole stack with id `id`.
+    pub fn remove_item(&mut self, id: ItemId) -> Option<Box<Item>> {
+        let idx = self.items.iter().position(|x| x.id == id)?;
+        Some(self.items.remove(idx))
+    }
+
+    /// Removes `count` items from the stack with id `id`, leaving the rest
+    /// of the stack in the inventory. The removed items get a new id unless
+    /// the whole stack was taken.
+    pub fn split_item(&mut self, id: ItemId, count: i32) -> Option<Box<Item>> {
+        let stack = self.items.iter_mut().find(|x| x.id == id)?;
+        if count >= stack.count {
+            return self.remove_item(id);
+        }
+        stack.count -= count;
+        let mut item = stack.clone();
+        item.count = count;
+        item.id = ItemId::next();
+        Some(item)
+    }
+
+    pub fn get_item(&self, id: ItemId) -> Option<&Item> {
+        self.items.iter().find(|x| x.id == id).map(Box::as_ref)
     }
 
-    pub fn remove_item(&mut self, idx: i32) -> Box<Item> {
-        self.items.remove(idx as usize)
+    /// Finds the item with id `id`, looking inside containers as well.
+    pub fn find_nested(&self, id: ItemId) -> Option<&Item> {
+        self.get_item(id).or_else(|| {
+            self.items
+                .iter()
+                .filter_map(|x| x.contents.as_ref())
+                .find_map(|x| x.find_nested(id))
+        })
     }
 
-    pub fn get_item(&self, idx: i32) -> &Box<Item> {
-        &self.items[idx as usize]
+    /// Finds the id of the first item with the given name.
+    pub fn find(&self, name: &str) -> Option<ItemId> {
+        self.items
+            .iter()
+            .find(|x| x.name == name)
+            .map(|x| x.id)
+    }
+
+    /// Total weight of everything in the inventory in kilograms.
+    pub fn weight(&self) -> f32 {
+        self.items.iter().map(|x| x.total_weight()).sum()
     }
 }
 
@@ -28,9 +101,38 @@ impl Inventory {
 pub enum ItemEffect {
     ChangeHealth(i32),
     Message(String),
+    /// Puts a status on the target for a number of ticks
+    ApplyStatus(StatusKind, i32),
+    /// Moves the target to a random spot at most this far away
+    Teleport(i32),
+    /// Marks every tile within this distance as explored
+    RevealMap(i32),
+    /// Applies the effect to every character within this distance of the
+    /// target position
+    Area(i32, Box<ItemEffect>),
 }
 
-#[derive(Clone, Debug)]
+impl ItemEffect {
+    /// Short explanation of what the effect does, for the examine screen.
+    /// Effects that only show a message aren't worth describing.
+    pub fn describe(&self) -> Option<String> {
+        match self {
+            ItemEffect::ChangeHealth(amt) if *amt >= 0 => Some(format!("Restores {} health", amt)),
+            ItemEffect::ChangeHealth(amt)  => Some(format!("Deals {} damage", -amt)),
+            ItemEffect::Message(_)         => None,
+            ItemEffect::ApplyStatus(kind, turns) => Some(
+                format!("Leaves the target {} for {} turns", kind.name(), turns)),
+            ItemEffect::Teleport(range)    => Some(
+                format!("Teleports the user up to {} tiles away", range)),
+            ItemEffect::RevealMap(range)   => Some(
+                format!("Reveals the map within {} tiles", range)),
+            ItemEffect::Area(radius, inner) => inner.describe().map(|x| 
+                format!("{} (everyone within {} tiles)", x, radius)),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
 pub enum WearLoc {
     Head,
     Torso,
@@ -40,33 +142,633 @@ pub enum WearLoc {
     Hand,
 }
 
+impl WearLoc {
+    /// Number of items that can be worn at this location at the same time.
+    pub fn slots(self) -> usize {
+        match self {
+            WearLoc::Hand => 2,
+            _             => 1,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            WearLoc::Head      => "head",
+            WearLoc::Torso     => "torso",
+            WearLoc::Legs      => "legs",
+            WearLoc::Shoulders => "shoulders",
+            WearLoc::Hands     => "hands",
+            WearLoc::Hand      => "hand",
+        }
+    }
+}
+
+/// Items worn or wielded by a character. Every item takes up one slot for
+/// each location in its `wearable` list, so an item that lists `Hand` twice
+/// needs both hands.
+#[derive(Clone, Debug)]
+pub struct Equipment {
+    pub items: Vec<Item>,
+}
+
+impl Equipment {
+    pub fn new() -> Self {
+        Equipment {
+            items: vec![],
+        }
+    }
+
+    /// Number of slots taken up at location `loc`.
+    pub fn used_slots(&self, loc: WearLoc) -> usize {
+        self.items
+            .iter()
+            .map(|x| x.wearable.iter().filter(|l| **l == loc).count())
+            .sum()
+    }
+
+    /// Checks whether `item` can be put on without taking anything off.
+    pub fn fits(&self, item: &Item) -> bool {
+        item.wearable.iter().all(|loc| {
+            let needed = item.wearable.iter().filter(|l| *l == loc).count();
+            self.used_slots(*loc) + needed <= loc.slots()
+        })
+    }
+
+    /// Puts on `item`, taking off whatever is in the way. Returns the items
+    /// that were taken off.
+    pub fn equip(&mut self, item: Item) -> Vec<Item> {
+        assert!(!item.wearable.is_empty());
+        let mut removed = vec![];
+        while !self.fits(&item) {
+            let idx = self.items
+                .iter()
+                .position(|x| x.wearable.iter().any(|l| item.wearable.contains(l)))
+                .expect("Nothing to take off");
+            removed.push(self.items.remove(idx));
+        }
+        self.items.push(item);
+        removed
+    }
+
+    pub fn unequip(&mut self, id: ItemId) -> Option<Item> {
+        let idx = self.items.iter().position(|x| x.id == id)?;
+        Some(self.items.remove(idx))
+    }
+
+    /// Total weight of everything worn in kilograms.
+    pub fn weight(&self) -> f32 {
+        self.items.iter().map(|x| x.total_weight()).sum()
+    }
+
+    /// Damage absorbed from hits to location `loc`.
+    pub fn armor_at(&self, loc: WearLoc) -> i32 {
+        self.items
+            .iter()
+            .filter(|x| x.wearable.contains(&loc))
+            .map(|x| x.armor)
+            .sum()
+    }
+
+    /// Sum of the stat changes of all worn items.
+    pub fn bonus(&self) -> StatBlock {
+        self.items
+            .iter()
+            .fold(StatBlock::zero(), |acc, x| acc + x.bonus)
+    }
+
+    /// Finds the wielded item that is used in close combat.
+    pub fn melee_weapon(&self) -> Option<&Item> {
+        self.items
+            .iter()
+            .find(|x| x.melee.is_some())
+    }
+
+    /// Finds the wielded item that fires ammunition.
+    pub fn firearm(&self) -> Option<&Item> {
+        self.items
+            .iter()
+            .find(|x| x.ranged.as_ref().is_some_and(|r| r.ammo.is_some()))
+    }
+}
+
+/// Properties of items that can hit things from a distance.
+#[derive(Clone, Debug)]
+pub struct RangedWeapon {
+    pub damage: Dice,
+    pub range: i32,
+    /// Name of the ammunition item used up by every shot. Weapons without
+    /// ammunition are thrown themselves.
+    pub ammo: Option<String>,
+}
+
+/// Properties of items that can be used to hit things in close combat.
+#[derive(Clone, Debug)]
+pub struct MeleeWeapon {
+    pub damage: Dice,
+    /// Verb used in attack messages, e.g. "stabs"
+    pub verb: String,
+}
+
 #[derive(Clone, Debug)]
 pub struct Item {
+    pub id: ItemId,
     pub name: String,
+    /// Glyph shown when the item lies on the ground
+    pub char: char,
+    pub color: Color,
     pub description: String,
     pub on_use: Vec<ItemEffect>,
     pub wearable: Vec<WearLoc>,
     pub consumable: bool,
+    /// Whether the effects of the item are aimed at somebody else
+    pub targeted: bool,
+    pub ranged: Option<RangedWeapon>,
+    pub melee: Option<MeleeWeapon>,
+    /// Damage absorbed from hits to the locations covered by the item while
+    /// it is worn
+    pub armor: i32,
+    /// Stat changes while the item is worn
+    pub bonus: StatBlock,
+    /// Weight of a single item in kilograms
+    pub weight: f32,
+    /// Whether identical items are kept together in a single stack
+    pub stackable: bool,
+    /// Number of items in the stack
+    pub count: i32,
+    /// Items stored inside, if the item is a container
+    pub contents: Option<Inventory>,
+    /// Name of the key needed to open the container, if it is locked
+    pub lock: Option<String>,
+    /// Whether the item is furniture that can't be picked up
+    pub fixed: bool,
+    /// What a single item is worth in credits
+    pub value: i32,
 }
 
 impl Item {
     pub fn new(name: String, description: String) -> Self {
         Item {
+            id: ItemId::next(),
             name: name,
+            char: ')',
+            color: WHITE,
             description: description,
             on_use: vec![],
             wearable: vec![],
             consumable: false,
+            targeted: false,
+            ranged: None,
+            melee: None,
+            armor: 0,
+            bonus: StatBlock::zero(),
+            weight: 1.0,
+            stackable: false,
+            count: 1,
+            contents: None,
+            lock: None,
+            fixed: false,
+            value: 0,
         }
     }
 
+    /// Checks whether the item is made for throwing.
+    pub fn is_throwable(&self) -> bool {
+        self.ranged.as_ref().is_some_and(|r| r.ammo.is_none())
+    }
+
+    /// Checks whether `other` can be merged into the same stack as this item.
+    pub fn stacks_with(&self, other: &Item) -> bool {
+        self.stackable && other.stackable && self.name == other.name
+    }
+
+    /// Weight of the whole stack, including whatever is stored inside.
+    pub fn total_weight(&self) -> f32 {
+        let contents = self.contents.as_ref().map_or(0.0, |x| x.weight());
+        self.weight * self.count as f32 + contents
+    }
+
+    pub fn draw(&self, pos: Pos, con: &mut dyn Console) {
+        con.set_default_foreground(self.color);
+        con.put_char(pos.x, pos.y, self.char, BackgroundFlag::None);
+    }
+
+    /// Name of the item together with the size of the stack or the number
+    /// of items inside.
+    pub fn display_name(&self) -> String {
+        if self.count > 1 {
+            format!("{} (x{})", self.name, self.count)
+        } else if let Some(contents) = &self.contents {
+            format!("{} ({} items)", self.name, contents.items.len())
+        } else {
+            self.name.clone()
+        }
+    }
+
+    /// Creates a container that holds `items`.
+    fn container(name: &str, description: &str, items: Vec<Item>) -> Self {
+        let mut item = Item::new(name.to_string(), description.to_string());
+        let mut contents = Inventory::new();
+        for x in items {
+            contents.add_item(Box::new(x));
+        }
+        item.contents = Some(contents);
+        item
+    }
+
+    pub fn backpack() -> Self {
+        let mut item = Item::container(
+            "backpack",
+            "A canvas backpack with plenty of pockets.",
+            vec![]);
+        item.weight = 1.0;
+        item.char = '(';
+        item.color = DARK_SEPIA;
+        item.value = 30;
+        item
+    }
+
+    pub fn crate_box(items: Vec<Item>) -> Self {
+        let mut item = Item::container(
+            "crate",
+            "A wooden shipping crate with a loose lid.",
+            items);
+        item.fixed = true;
+        item.weight = 20.0;
+        item.char = '=';
+        item.color = DARK_ORANGE;
+        item
+    }
+
+    pub fn locker(items: Vec<Item>) -> Self {
+        let mut item = Item::container(
+            "locker",
+            "A dented steel locker.",
+            items);
+        item.fixed = true;
+        item.weight = 40.0;
+        item.lock = Some("locker key".to_string());
+        item.char = ']';
+        item.color = LIGHT_BLUE;
+        item
+    }
+
+    pub fn vending_machine(items: Vec<Item>) -> Self {
+        let mut item = Item::container(
+            "vending machine",
+            "A vending machine that stopped taking money long ago.",
+            items);
+        item.fixed = true;
+        item.weight = 200.0;
+        item.lock = Some("vending machine key".to_string());
+        item.char = '$';
+        item.color = LIGHT_GREEN;
+        item
+    }
+
+    /// Creates a key that opens containers locked with `lock`.
+    pub fn key(lock: &str) -> Self {
+        let mut item = Item::new(
+            lock.to_string(),
+            "A small key on a plastic tag.".to_string());
+        item.weight = 0.05;
+        item.stackable = true;
+        item.char = '-';
+        item.color = YELLOW;
+        item.value = 5;
+        item
+    }
+
     pub fn healing_potion() -> Self {
         Item {
+            id: ItemId::next(),
             name: "healing potion".to_string(),
+            char: '!',
+            color: DARK_RED,
             description: "It's a healing potion!".to_string(),
             on_use: vec![ItemEffect::ChangeHealth(3)],
             wearable: vec![],
             consumable: true,
+            targeted: false,
+            ranged: None,
+            melee: None,
+            armor: 0,
+            bonus: StatBlock::zero(),
+            weight: 0.3,
+            stackable: true,
+            count: 1,
+            contents: None,
+            lock: None,
+            fixed: false,
+            value: 25,
         }
     }
+
+    pub fn stim_pack() -> Self {
+        let mut item = Item::new(
+            "stim pack".to_string(),
+            "An injector full of combat stimulants.".to_string());
+        item.on_use = vec![
+            ItemEffect::ApplyStatus(StatusKind::Stimmed, 20),
+            ItemEffect::Message("Your heart starts racing.".to_string()),
+        ];
+        item.consumable = true;
+        item.weight = 0.2;
+        item.stackable = true;
+        item.char = '!';
+        item.color = LIGHT_BLUE;
+        item.value = 40;
+        item
+    }
+
+    pub fn steroid_shot() -> Self {
+        let mut item = Item::new(
+            "steroid shot".to_string(),
+            "A syringe of muscle enhancers of dubious origin.".to_string());
+        item.on_use = vec![
+            ItemEffect::ApplyStatus(StatusKind::Buffed(StatBlock::new(3, 0, 2)), 40),
+            ItemEffect::ChangeHealth(-1),
+        ];
+        item.consumable = true;
+        item.weight = 0.1;
+        item.stackable = true;
+        item.char = '!';
+        item.color = LIGHT_GREEN;
+        item.value = 35;
+        item
+    }
+
+    pub fn poison_dart() -> Self {
+        let mut item = Item::new(
+            "poison dart".to_string(),
+            "A dart coated in something green and sticky.".to_string());
+        item.on_use = vec![ItemEffect::ApplyStatus(StatusKind::Poisoned, 6)];
+        item.consumable = true;
+        item.targeted = true;
+        item.weight = 0.05;
+        item.stackable = true;
+        item.char = '/';
+        item.color = GREEN;
+        item.value = 10;
+        item
+    }
+
+    pub fn grenade() -> Self {
+        let mut item = Item::new(
+            "frag grenade".to_string(),
+            "A fragmentation grenade. Throw it far away.".to_string());
+        item.on_use = vec![
+            ItemEffect::Area(2, Box::new(ItemEffect::ChangeHealth(-6))),
+        ];
+        item.consumable = true;
+        item.targeted = true;
+        item.weight = 0.5;
+        item.stackable = true;
+        item.char = '*';
+        item.color = DARK_GREEN;
+        item.value = 60;
+        item
+    }
+
+    pub fn flashbang() -> Self {
+        let mut item = Item::new(
+            "flashbang".to_string(),
+            "A stun grenade that blinds everyone nearby.".to_string());
+        item.on_use = vec![
+            ItemEffect::Area(3, Box::new(ItemEffect::ApplyStatus(StatusKind::Blinded, 5))),
+            ItemEffect::Area(1, Box::new(ItemEffect::ApplyStatus(StatusKind::Stunned, 2))),
+        ];
+        item.consumable = true;
+        item.targeted = true;
+        item.weight = 0.4;
+        item.stackable = true;
+        item.char = '*';
+        item.color = LIGHT_GREY;
+        item.value = 45;
+        item
+    }
+
+    pub fn molotov() -> Self {
+        let mut item = Item::new(
+            "molotov cocktail".to_string(),
+            "A bottle of fuel with a rag stuffed in the neck.".to_string());
+        item.on_use = vec![
+            ItemEffect::Area(1, Box::new(ItemEffect::ApplyStatus(StatusKind::Burning, 4))),
+        ];
+        item.consumable = true;
+        item.targeted = true;
+        item.weight = 0.8;
+        item.stackable = true;
+        item.char = '*';
+        item.color = ORANGE;
+        item.value = 20;
+        item
+    }
+
+    pub fn emp_grenade() -> Self {
+        let mut item = Item::new(
+            "EMP grenade".to_string(),
+            "Scrambles every piece of electronics nearby, cyberware included.".to_string());
+        item.on_use = vec![
+            ItemEffect::Area(3, Box::new(ItemEffect::ApplyStatus(StatusKind::Hacked, 10))),
+        ];
+        item.consumable = true;
+        item.targeted = true;
+        item.weight = 0.5;
+        item.stackable = true;
+        item.char = '*';
+        item.color = CYAN;
+        item.value = 80;
+        item
+    }
+
+    pub fn teleporter() -> Self {
+        let mut item = Item::new(
+            "prototype teleporter".to_string(),
+            "An unstable device that sends its user somewhere nearby.".to_string());
+        item.on_use = vec![ItemEffect::Teleport(10)];
+        item.consumable = true;
+        item.weight = 1.5;
+        item.char = '&';
+        item.color = MAGENTA;
+        item.value = 200;
+        item
+    }
+
+    pub fn map_chip() -> Self {
+        let mut item = Item::new(
+            "map chip".to_string(),
+            "A data chip with a map of the surrounding blocks.".to_string());
+        item.on_use = vec![ItemEffect::RevealMap(40)];
+        item.consumable = true;
+        item.weight = 0.05;
+        item.stackable = true;
+        item.char = '?';
+        item.color = LIGHT_CYAN;
+        item.value = 30;
+        item
+    }
+
+    pub fn pistol() -> Self {
+        let mut item = Item::new(
+            "9mm pistol".to_string(),
+            "A battered semi-automatic pistol.".to_string());
+        item.ranged = Some(RangedWeapon {
+            damage: Dice::new(1, 6, 0),
+            range: 12,
+            ammo: Some("9mm round".to_string()),
+        });
+        item.wearable = vec![WearLoc::Hand];
+        item.weight = 1.0;
+        item.value = 150;
+        item
+    }
+
+    pub fn shotgun() -> Self {
+        let mut item = Item::new(
+            "shotgun".to_string(),
+            "A sawn-off double barrel shotgun. It needs both hands.".to_string());
+        item.ranged = Some(RangedWeapon {
+            damage: Dice::new(2, 6, 0),
+            range: 6,
+            ammo: Some("shotgun shell".to_string()),
+        });
+        item.wearable = vec![WearLoc::Hand, WearLoc::Hand];
+        item.weight = 3.0;
+        item.value = 250;
+        item
+    }
+
+    pub fn shotgun_shell() -> Self {
+        let mut item = Item::new(
+            "shotgun shell".to_string(),
+            "Ammunition for shotguns.".to_string());
+        item.weight = 0.05;
+        item.stackable = true;
+        item.char = '=';
+        item.color = LIGHT_RED;
+        item.value = 3;
+        item
+    }
+
+    pub fn leather_jacket() -> Self {
+        let mut item = Item::new(
+            "leather jacket".to_string(),
+            "A worn leather jacket with padded shoulders.".to_string());
+        item.wearable = vec![WearLoc::Torso, WearLoc::Shoulders];
+        item.armor = 1;
+        item.bonus = StatBlock::new(0, 0, 2);
+        item.weight = 2.5;
+        item.char = '[';
+        item.color = DARK_SEPIA;
+        item.value = 60;
+        item
+    }
+
+    pub fn helmet() -> Self {
+        let mut item = Item::new(
+            "riot helmet".to_string(),
+            "A scratched police riot helmet.".to_string());
+        item.wearable = vec![WearLoc::Head];
+        item.armor = 2;
+        item.weight = 1.5;
+        item.char = '[';
+        item.color = LIGHT_GREY;
+        item.value = 50;
+        item
+    }
+
+    pub fn pistol_round() -> Self {
+        let mut item = Item::new(
+            "9mm round".to_string(),
+            "Ammunition for 9mm pistols.".to_string());
+        item.weight = 0.02;
+        item.stackable = true;
+        item.char = '=';
+        item.color = LIGHT_YELLOW;
+        item.value = 2;
+        item
+    }
+
+    pub fn throwing_knife() -> Self {
+        let mut item = Item::new(
+            "throwing knife".to_string(),
+            "A small balanced knife.".to_string());
+        item.ranged = Some(RangedWeapon {
+            damage: Dice::new(1, 4, 0),
+            range: 6,
+            ammo: None,
+        });
+        item.melee = Some(MeleeWeapon {
+            damage: Dice::new(1, 3, 0),
+            verb: "stabs".to_string(),
+        });
+        item.wearable = vec![WearLoc::Hand];
+        item.weight = 0.2;
+        item.stackable = true;
+        item.char = '/';
+        item.color = LIGHT_GREY;
+        item.value = 15;
+        item
+    }
+
+    pub fn crowbar() -> Self {
+        let mut item = Item::new(
+            "crowbar".to_string(),
+            "A heavy steel crowbar.".to_string());
+        item.melee = Some(MeleeWeapon {
+            damage: Dice::new(1, 6, 1),
+            verb: "bashes".to_string(),
+        });
+        item.wearable = vec![WearLoc::Hand];
+        item.weight = 2.5;
+        item.char = '\\';
+        item.color = GREY;
+        item.value = 10;
+        item
+    }
+
+    pub fn fire_axe() -> Self {
+        let mut item = Item::new(
+            "fire axe".to_string(),
+            "A long red fire axe. It needs both hands and some muscle.".to_string());
+        item.melee = Some(MeleeWeapon {
+            damage: Dice::new(2, 6, 0),
+            verb: "chops".to_string(),
+        });
+        item.wearable = vec![WearLoc::Hand, WearLoc::Hand];
+        item.bonus = StatBlock::new(0, -2, 0);
+        item.weight = 3.5;
+        item.char = '\\';
+        item.color = RED;
+        item.value = 40;
+        item
+    }
+
+    pub fn power_gloves() -> Self {
+        let mut item = Item::new(
+            "power gloves".to_string(),
+            "Gloves with servo-assisted knuckles.".to_string());
+        item.wearable = vec![WearLoc::Hands];
+        item.armor = 1;
+        item.bonus = StatBlock::new(2, 0, 0);
+        item.weight = 1.0;
+        item.char = '[';
+        item.color = YELLOW;
+        item.value = 120;
+        item
+    }
+
+    pub fn kevlar_pants() -> Self {
+        let mut item = Item::new(
+            "kevlar pants".to_string(),
+            "Heavy pants with kevlar lining.".to_string());
+        item.wearable = vec![WearLoc::Legs];
+        item.armor = 2;
+        item.bonus = StatBlock::new(0, -1, 0);
+        item.weight = 4.0;
+        item.char = '[';
+        item.color = DARK_GREY;
+        item.value = 90;
+        item
+    }
 }
@@ -0,0 +1,92 @@
+use crate::rustpunk::pos::Pos;
+
+pub const VIEWPORT_WIDTH: i32 = 70;
+pub const VIEWPORT_HEIGHT: i32 = 42;
+pub const SIDEBAR_WIDTH: i32 = 26;
+pub const LOG_HEIGHT: i32 = 10;
+/// The map viewport and message panel are stacked on the left, the status
+/// sidebar takes up the whole height on the right.
+pub const SCREEN_WIDTH: i32 = VIEWPORT_WIDTH + SIDEBAR_WIDTH;
+pub const SCREEN_HEIGHT: i32 = VIEWPORT_HEIGHT + LOG_HEIGHT;
+
+/// Region of the screen in console cells.
+#[derive(Clone, Copy, Debug)]
+pub struct Rect {
+    pub x: i32,
+    pub y: i32,
+    pub w: i32,
+    pub h: i32,
+}
+
+impl Rect {
+    /// Checks whether the screen position `pos` lies inside the region.
+    pub fn contains(&self, pos: Pos) -> bool {
+        pos.x >= self.x && pos.x < self.x + self.w &&
+        pos.y >= self.y && pos.y < self.y + self.h
+    }
+}
+
+pub const VIEWPORT: Rect = Rect { x: 0, y: 0, w: VIEWPORT_WIDTH, h: VIEWPORT_HEIGHT };
+pub const SIDEBAR: Rect = Rect { x: VIEWPORT_WIDTH, y: 0, w: SIDEBAR_WIDTH, h: SCREEN_HEIGHT };
+pub const LOG: Rect = Rect { x: 0, y: VIEWPORT_HEIGHT, w: VIEWPORT_WIDTH, h: LOG_HEIGHT };
+
+/// Splits `text` into lines of at most `width` characters, breaking at
+/// spaces where possible. Words longer than a line are cut.
+pub fn wrap_text(text: &str, width: i32) -> Vec<String> {
+    let width = width.max(1) as usize;
+    let mut lines = vec![];
+    let mut line = String::new();
+    for word in text.split_whitespace() {
+        let mut word: Vec<char> = word.chars().collect();
+        let len = line.chars().count();
+        if len > 0 && len + 1 + word.len() > width {
+            lines.push(line);
+            line = String::new();
+        }
+        while word.len() > width {
+            lines.push(word.drain(..width).collect());
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.extend(word);
+    }
+    if !line.is_empty() || lines.is_empty() {
+        lines.push(line);
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn breaks_lines_at_spaces() {
+        assert_eq!(wrap_text("the wolf bites you", 9), vec!["the wolf", "bites you"]);
+        assert_eq!(wrap_text("short", 20), vec!["short"]);
+    }
+
+    #[test]
+    fn collapses_extra_whitespace() {
+        assert_eq!(wrap_text("  a   b  ", 10), vec!["a b"]);
+    }
+
+    #[test]
+    fn cuts_words_longer_than_a_line() {
+        assert_eq!(wrap_text("abcdefgh ij", 3), vec!["abc", "def", "gh", "ij"]);
+        assert_eq!(wrap_text("ab cdefg", 3), vec!["ab", "cde", "fg"]);
+    }
+
+    #[test]
+    fn empty_text_gives_one_empty_line() {
+        assert_eq!(wrap_text("", 10), vec![""]);
+        assert_eq!(wrap_text("   ", 10), vec![""]);
+    }
+
+    #[test]
+    fn width_is_at_least_one() {
+        assert_eq!(wrap_text("ab", 0), vec!["a", "b"]);
+        assert_eq!(wrap_text("ab", -5), vec!["a", "b"]);
+    }
+}
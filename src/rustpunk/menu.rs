@@ -0,0 +1,382 @@
+use core::cmp::*;
+use tcod::colors::*;
+use tcod::console::*;
+
+use crate::rustpunk::pos::*;
+use crate::rustpunk::view::Command;
+use crate::rustpunk::layout::Rect;
+
+const HOTKEYS: &str = "abcdefghijklmnopqrstuvwxyz";
+const HEADER_COLOR: Color = LIGHT_BLUE;
+const DISABLED_COLOR: Color = DARK_GREY;
+const HIGHLIGHT_COLOR: Color = YELLOW;
+
+/// A line in a menu. Entries without a value are headers.
+pub struct MenuEntry<T> {
+    pub text: String,
+    pub value: Option<T>,
+    /// Disabled entries are shown, but can't be chosen
+    pub enabled: bool,
+    /// Shown with a `+` in front, e.g. for items marked for dropping
+    pub marked: bool,
+}
+
+impl<T> MenuEntry<T> {
+    pub fn new(text: String, value: T) -> Self {
+        MenuEntry {
+            text: text,
+            value: Some(value),
+            enabled: true,
+            marked: false,
+        }
+    }
+
+    pub fn header(text: String) -> Self {
+        MenuEntry {
+            text: text,
+            value: None,
+            enabled: true,
+            marked: false,
+        }
+    }
+
+    pub fn enabled(mut self, enabled: bool) -> Self {
+        self.enabled = enabled;
+        self
+    }
+
+    pub fn marked(mut self, marked: bool) -> Self {
+        self.marked = marked;
+        self
+    }
+
+    fn is_header(&self) -> bool {
+        self.value.is_none()
+    }
+
+    fn selectable(&self) -> bool {
+        !self.is_header() && self.enabled
+    }
+}
+
+/// What a command did to a menu.
+pub enum MenuEvent<T> {
+    /// The cursor moved, or nothing the view needs to know about happened
+    Moved,
+    /// The entry with this value was chosen with enter or its hotkey
+    Chosen(T),
+    /// The command has nothing to do with menus
+    Ignored,
+}
+
+/// A scrolling list of entries with a cursor. Every entry that is not a
+/// header gets a letter that chooses it directly, and entries can be
+/// clicked.
+pub struct Menu<T> {
+    entries: Vec<MenuEntry<T>>,
+    cursor: usize,
+    /// Index of the first entry that is shown
+    scroll: usize,
+    /// Region of the screen the menu is drawn in, one entry per row
+    area: Rect,
+}
+
+impl<T: Clone> Menu<T> {
+    pub fn new(entries: Vec<MenuEntry<T>>, area: Rect) -> Self {
+        let mut menu = Menu {
+            entries: vec![],
+            cursor: 0,
+            scroll: 0,
+            area: Rect { h: max(1, area.h), ..area },
+        };
+        menu.set_entries(entries);
+        menu
+    }
+
+    /// Replaces the entries, keeping the cursor where it was as far as
+    /// possible.
+    pub fn set_entries(&mut self, entries: Vec<MenuEntry<T>>) {
+        self.entries = entries;
+        self.cursor = min(self.cursor, self.entries.len().saturating_sub(1));
+        if !self.is_selectable(self.cursor) {
+            self.cursor = self.find_selectable(self.cursor, 1)
+                .or(self.find_selectable(self.cursor, -1))
+                .unwrap_or(self.cursor);
+        }
+        self.fix_scroll();
+    }
+
+    /// Value of the entry under the cursor.
+    pub fn selected(&self) -> Option<T> {
+        self.entries
+            .get(self.cursor)
+            .filter(|x| x.selectable())
+            .and_then(|x| x.value.clone())
+    }
+
+    /// Number of rows the menu takes up on the screen.
+    pub fn rows(&self) -> i32 {
+        min(self.entries.len() as i32, self.area.h)
+    }
+
+    pub fn area(&self) -> Rect {
+        self.area
+    }
+
+    /// Checks whether the screen position `pos` is on one of the entries.
+    pub fn contains(&self, pos: Pos) -> bool {
+        Rect { h: self.rows(), ..self.area }.contains(pos)
+    }
+
+    /// Row of the cursor, counted from the top of the menu.
+    pub fn cursor_row(&self) -> i32 {
+        self.cursor as i32 - self.scroll as i32
+    }
+
+    pub fn handle_command(&mut self, com: Command) -> MenuEvent<T> {
+        match com {
+            Command::Move(Dir::N) => self.move_cursor(-1),
+            Command::Move(Dir::S) => self.move_cursor(1),
+            Command::ScrollUp     => self.move_cursor(-1),
+            Command::ScrollDown   => self.move_cursor(1),
+            Command::PageUp       => self.move_cursor(-self.area.h),
+            Command::PageDown     => self.move_cursor(self.area.h),
+            Command::Select       => return match self.selected() {
+                Some(value) => MenuEvent::Chosen(value),
+                None        => MenuEvent::Moved,
+            },
+            Command::Hotkey(key)  => {
+                let found = (0..self.entries.len()).find(|i| self.hotkey(*i) == Some(key));
+                if let Some(i) = found.filter(|i| self.is_selectable(*i)) {
+                    self.cursor = i;
+                    self.fix_scroll();
+                    return MenuEvent::Chosen(self.entries[i].value.clone().unwrap());
+                }
+            }
+            Command::Click(pos) if self.contains(pos) => {
+                let i = self.scroll + (pos.y - self.area.y) as usize;
+                if self.is_selectable(i) {
+                    self.cursor = i;
+                    return MenuEvent::Chosen(self.entries[i].value.clone().unwrap());
+                }
+            }
+            _ => return MenuEvent::Ignored,
+        }
+        MenuEvent::Moved
+    }
+
+    /// Draws the visible entries. The cursor is only shown when the menu is
+    /// `focused`.
+    pub fn render(&self, con: &mut Offscreen, focused: bool) {
+        let Rect { x, y, w: width, h: height } = self.area;
+        let end = min(self.entries.len(), self.scroll + height as usize);
+        for i in self.scroll..end {
+            let entry = &self.entries[i];
+            let row = y + (i - self.scroll) as i32;
+            if entry.is_header() {
+                con.set_default_foreground(HEADER_COLOR);
+                con.print(x + 1, row, &entry.text);
+                continue;
+            }
+            let selected = focused && i == self.cursor;
+            let color = if !entry.enabled {
+                DISABLED_COLOR
+            } else if selected {
+                HIGHLIGHT_COLOR
+            } else {
+                WHITE
+            };
+            con.set_default_foreground(color);
+            if selected {
+                con.put_char(x, row, '>', BackgroundFlag::None);
+            }
+            if entry.marked {
+                con.put_char(x + 1, row, '+', BackgroundFlag::None);
+            }
+            let key = self.hotkey(i).map_or("   ".to_string(), |c| format!("{}) ", c));
+            let text: String = format!("{}{}", key, entry.text)
+                .chars()
+                .take(max(0, width - 3) as usize)
+                .collect();
+            con.print(x + 2, row, text);
+        }
+        // Hint at entries that are scrolled out of sight
+        con.set_default_foreground(HEADER_COLOR);
+        if self.scroll > 0 {
+            con.put_char(x + width - 1, y, '^', BackgroundFlag::None);
+        }
+        if end < self.entries.len() {
+            con.put_char(x + width - 1, y + height - 1, 'v', BackgroundFlag::None);
+        }
+        con.set_default_foreground(WHITE);
+    }
+
+    fn is_selectable(&self, i: usize) -> bool {
+        self.entries.get(i).is_some_and(|x| x.selectable())
+    }
+
+    /// Letter of the entry at index `i`. Headers have none.
+    fn hotkey(&self, i: usize) -> Option<char> {
+        if self.entries[i].is_header() {
+            return None;
+        }
+        let n = self.entries[..i].iter().filter(|x| !x.is_header()).count();
+        HOTKEYS.chars().nth(n)
+    }
+
+    /// Index of the closest selectable entry after `from` in the direction
+    /// of `step`.
+    fn find_selectable(&self, from: usize, step: i32) -> Option<usize> {
+        let mut i = from as i32 + step;
+        while i >= 0 && i < self.entries.len() as i32 {
+            if self.is_selectable(i as usize) {
+                return Some(i as usize);
+            }
+            i += step;
+        }
+        None
+    }
+
+    /// Moves the cursor over `steps` selectable entries, stopping at the
+    /// ends of the list.
+    fn move_cursor(&mut self, steps: i32) {
+        for _ in 0..steps.abs() {
+            match self.find_selectable(self.cursor, steps.signum()) {
+                Some(i) => self.cursor = i,
+                None    => break,
+            }
+        }
+        self.fix_scroll();
+    }
+
+    /// Scrolls so that the cursor is visible, along with the headers above
+    /// the first entry.
+    fn fix_scroll(&mut self) {
+        let height = self.area.h as usize;
+        self.scroll = min(self.scroll, self.entries.len().saturating_sub(height));
+        if self.find_selectable(self.cursor, -1).is_none() {
+            self.scroll = 0;
+        }
+        if self.cursor < self.scroll {
+            self.scroll = self.cursor;
+        }
+        if self.cursor >= self.scroll + height {
+            self.scroll = self.cursor + 1 - height;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn area(h: i32) -> Rect {
+        Rect { x: 10, y: 5, w: 20, h: h }
+    }
+
+    fn numbers(n: i32) -> Vec<MenuEntry<i32>> {
+        (0..n).map(|i| MenuEntry::new(i.to_string(), i)).collect()
+    }
+
+    fn chosen(event: MenuEvent<i32>) -> Option<i32> {
+        match event {
+            MenuEvent::Chosen(x) => Some(x),
+            _                    => None,
+        }
+    }
+
+    #[test]
+    fn hotkeys_skip_headers() {
+        let entries = vec![
+            MenuEntry::header("Worn".to_string()),
+            MenuEntry::new("a".to_string(), 1),
+            MenuEntry::header("Carried".to_string()),
+            MenuEntry::new("b".to_string(), 2),
+        ];
+        let mut menu = Menu::new(entries, area(10));
+        assert_eq!(menu.hotkey(0), None);
+        assert_eq!(menu.hotkey(3), Some('b'));
+        assert_eq!(chosen(menu.handle_command(Command::Hotkey('b'))), Some(2));
+        assert_eq!(menu.selected(), Some(2));
+    }
+
+    #[test]
+    fn cursor_starts_on_first_selectable_entry() {
+        let entries = vec![
+            MenuEntry::header("Header".to_string()),
+            MenuEntry::new("off".to_string(), 1).enabled(false),
+            MenuEntry::new("on".to_string(), 2),
+        ];
+        let menu = Menu::new(entries, area(10));
+        assert_eq!(menu.selected(), Some(2));
+    }
+
+    #[test]
+    fn disabled_entries_are_skipped_and_cannot_be_chosen() {
+        let entries = vec![
+            MenuEntry::new("a".to_string(), 1),
+            MenuEntry::new("b".to_string(), 2).enabled(false),
+            MenuEntry::new("c".to_string(), 3),
+        ];
+        let mut menu = Menu::new(entries, area(10));
+        menu.handle_command(Command::Move(Dir::S));
+        assert_eq!(menu.selected(), Some(3));
+        assert_eq!(chosen(menu.handle_command(Command::Hotkey('b'))), None);
+        assert_eq!(menu.selected(), Some(3));
+    }
+
+    #[test]
+    fn cursor_stops_at_the_ends() {
+        let mut menu = Menu::new(numbers(3), area(10));
+        menu.handle_command(Command::Move(Dir::N));
+        assert_eq!(menu.selected(), Some(0));
+        for _ in 0..5 {
+            menu.handle_command(Command::Move(Dir::S));
+        }
+        assert_eq!(menu.selected(), Some(2));
+    }
+
+    #[test]
+    fn scrolling_keeps_the_cursor_visible() {
+        let mut menu = Menu::new(numbers(10), area(3));
+        assert_eq!(menu.rows(), 3);
+        for _ in 0..4 {
+            menu.handle_command(Command::Move(Dir::S));
+        }
+        assert_eq!(menu.selected(), Some(4));
+        assert_eq!(menu.cursor_row(), 2);
+        menu.handle_command(Command::PageUp);
+        assert_eq!(menu.selected(), Some(1));
+        assert_eq!(menu.cursor_row(), 0);
+    }
+
+    #[test]
+    fn paging_moves_a_screen_at_a_time() {
+        let mut menu = Menu::new(numbers(10), area(4));
+        menu.handle_command(Command::PageDown);
+        assert_eq!(menu.selected(), Some(4));
+        menu.handle_command(Command::PageDown);
+        menu.handle_command(Command::PageDown);
+        assert_eq!(menu.selected(), Some(9));
+    }
+
+    #[test]
+    fn clicks_choose_the_entry_on_that_row() {
+        let mut menu = Menu::new(numbers(10), area(3));
+        menu.handle_command(Command::PageDown);
+        // Rows now show entries 1 to 3
+        assert_eq!(chosen(menu.handle_command(Command::Click(Pos::new(12, 6)))), Some(2));
+        assert!(matches!(menu.handle_command(Command::Click(Pos::new(12, 8))), MenuEvent::Ignored));
+    }
+
+    #[test]
+    fn set_entries_keeps_the_cursor_in_range() {
+        let mut menu = Menu::new(numbers(5), area(10));
+        menu.handle_command(Command::PageDown);
+        assert_eq!(menu.selected(), Some(4));
+        menu.set_entries(numbers(2));
+        assert_eq!(menu.selected(), Some(1));
+        menu.set_entries(vec![]);
+        assert_eq!(menu.selected(), None);
+    }
+}
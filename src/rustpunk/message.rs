@@ -1,16 +1,169 @@
 use tcod::colors::*;
 
+/// How many messages are kept in the history by default.
+pub const DEFAULT_MESSAGE_CAP: usize = 500;
+
+/// What a message is about, used for filtering the message log.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Category {
+    /// Attacks and deaths
+    Combat,
+    /// The player getting hurt
+    DamageTaken,
+    /// Picking up, dropping and using items
+    Loot,
+    /// Things that went wrong or need the player's attention
+    Warning,
+    /// Everything else
+    Flavor,
+}
+
+impl Category {
+    pub fn name(self) -> &'static str {
+        match self {
+            Category::Combat      => "combat",
+            Category::DamageTaken => "damage taken",
+            Category::Loot        => "loot",
+            Category::Warning     => "warnings",
+            Category::Flavor      => "flavor",
+        }
+    }
+}
+
+/// How important a message is. Decides the color it is shown in.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Severity {
+    Info,
+    Good,
+    Notice,
+    Bad,
+    Critical,
+}
+
+impl Severity {
+    pub fn color(self) -> Color {
+        match self {
+            Severity::Info     => WHITE,
+            Severity::Good     => LIGHT_GREEN,
+            Severity::Notice   => LIGHT_YELLOW,
+            Severity::Bad      => LIGHT_RED,
+            Severity::Critical => RED,
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct Message {
     pub text: String,
-    pub color: Color,
+    pub category: Category,
+    pub severity: Severity,
+    /// Turn on which the message was added to the log
+    pub turn: u32,
+    /// How many times in a row the message was added
+    pub repeats: i32,
 }
 
 impl Message {
-    pub fn new(text: String) -> Self {
+    pub fn new(text: String, category: Category, severity: Severity) -> Self {
         Message {
             text: text,
-            color: WHITE,
+            category: category,
+            severity: severity,
+            turn: 0,
+            repeats: 1,
+        }
+    }
+
+    pub fn combat(text: String) -> Self {
+        Message::new(text, Category::Combat, Severity::Info)
+    }
+
+    pub fn damage_taken(text: String) -> Self {
+        Message::new(text, Category::DamageTaken, Severity::Bad)
+    }
+
+    pub fn loot(text: String) -> Self {
+        Message::new(text, Category::Loot, Severity::Info)
+    }
+
+    pub fn warning(text: String) -> Self {
+        Message::new(text, Category::Warning, Severity::Notice)
+    }
+
+    pub fn flavor(text: String) -> Self {
+        Message::new(text, Category::Flavor, Severity::Info)
+    }
+
+    /// Announces the death of the character called `name`.
+    pub fn death(name: &str) -> Self {
+        Message::new(format!("{} dies!", name), Category::Combat, Severity::Critical)
+    }
+
+    pub fn with_severity(mut self, severity: Severity) -> Self {
+        self.severity = severity;
+        self
+    }
+
+    /// Text of the message with the number of repeats, e.g. "Wolf bites
+    /// you. x3".
+    pub fn display_text(&self) -> String {
+        if self.repeats > 1 {
+            format!("{} x{}", self.text, self.repeats)
+        } else {
+            self.text.clone()
+        }
+    }
+}
+
+/// History of messages. Repeated messages are collapsed into one and the
+/// oldest messages are forgotten once there are more than `cap` of them.
+pub struct MessageLog {
+    messages: Vec<Message>,
+    cap: usize,
+    /// Turn that new messages are stamped with
+    turn: u32,
+}
+
+impl MessageLog {
+    pub fn new(cap: usize) -> Self {
+        MessageLog {
+            messages: vec![],
+            cap: cap,
+            turn: 0,
+        }
+    }
+
+    pub fn push(&mut self, mut msg: Message) {
+        msg.turn = self.turn;
+        match self.messages.last_mut() {
+            Some(last) if last.text == msg.text => {
+                last.repeats += 1;
+                last.turn = msg.turn;
+            }
+            _ => self.messages.push(msg),
+        }
+        self.trim();
+    }
+
+    /// Changes how many messages are kept, forgetting the oldest ones if
+    /// there are too many.
+    pub fn set_cap(&mut self, cap: usize) {
+        self.cap = cap;
+        self.trim();
+    }
+
+    pub fn set_turn(&mut self, turn: u32) {
+        self.turn = turn;
+    }
+
+    pub fn messages(&self) -> &Vec<Message> {
+        &self.messages
+    }
+
+    fn trim(&mut self) {
+        if self.messages.len() > self.cap {
+            let excess = self.messages.len() - self.cap;
+            self.messages.drain(..excess);
         }
     }
 }
@@ -1,32 +1,97 @@
 use core::cmp::min;
+use core::cmp::max;
+use std::ops::Add;
 use crate::rustpunk::pos::*;
-use crate::rustpunk::message::Message;
-use crate::rustpunk::gamestate::GameState;
+use crate::rustpunk::message::*;
+use crate::rustpunk::gamestate::*;
 use crate::rustpunk::item::*;
+use crate::rustpunk::ai::*;
+use crate::rustpunk::combat::*;
+use crate::rustpunk::status::*;
 
 use tcod::colors::*;
 use tcod::console::*;
 use tcod::random::*;
 
+/// Kilograms a character can carry without slowing down for each point of
+/// strength.
+pub const CARRY_PER_STR: f32 = 3.0;
+/// Characters carrying more than this many times their capacity can't move.
+pub const OVERLOAD_FACTOR: f32 = 2.0;
+
 #[derive(Clone, Copy, Debug)]
 pub enum Action {
     Idle,
     Move(Dir),
-    GetItem(i32),
-    DropItem(i32),
+    /// Pick up an item lying on the same tile
+    GetItem(ItemId),
+    /// Drop a number of items from a stack in the inventory
+    DropItem(ItemId, i32),
+    UseItem(ItemId),
+    /// Shoot the wielded firearm at a position
+    Shoot(Pos),
+    /// Throw an item from the inventory at a position
+    Throw(ItemId, Pos),
+    /// Use an item from the inventory on a position
+    UseItemOn(ItemId, Pos),
+    /// Put on an item from the inventory
+    Equip(ItemId),
+    /// Take off an equipped item
+    Unequip(ItemId),
+    /// Put a number of items from the inventory into a container
+    PutItem(ItemId, ItemId, i32),
+    /// Take an item out of a container
+    TakeItem(ItemId, ItemId),
+}
+
+impl Action {
+    /// Amount of energy the action takes.
+    pub fn cost(self) -> i32 {
+        match self {
+            Action::Idle        => 100,
+            Action::Move(_)     => 100,
+            Action::GetItem(_)  => 50,
+            Action::DropItem(_, _) => 50,
+            Action::UseItem(_)  => 100,
+            Action::Shoot(_)    => 100,
+            Action::Throw(_, _) => 100,
+            Action::UseItemOn(_, _) => 100,
+            Action::Equip(_)    => 100,
+            Action::Unequip(_)  => 100,
+            Action::PutItem(_, _, _) => 50,
+            Action::TakeItem(_, _)   => 50,
+        }
+    }
 }
 
 #[derive(Clone, Copy, PartialEq, Debug)]
 pub enum Faction {
     Player,
     Wolves,
+    Raiders,
     Neutral,
 }
 
-#[derive(Clone, Copy, Debug)]
+impl Faction {
+    pub fn name(self) -> &'static str {
+        match self {
+            Faction::Player  => "player",
+            Faction::Wolves  => "wolves",
+            Faction::Raiders => "raiders",
+            Faction::Neutral => "neutral",
+        }
+    }
+
+    /// Checks whether members of this faction attack members of the other one.
+    pub fn is_hostile(self, other: Faction) -> bool {
+        self != other && self != Faction::Neutral && other != Faction::Neutral
+    }
+}
+
+#[derive(Clone, Debug)]
 pub enum Controller {
     Dummy,
-    AggressiveAI{last_player_pos: Option<Pos>},
+    AI(Brain),
     PlayerController{action: Action},
 }
 
@@ -37,43 +102,55 @@ impl Controller {
         }
     }
 
-    pub fn aggressive_ai() -> Self {
-        Controller::AggressiveAI {
-            last_player_pos: None,
-        }
+    /// Creates an AI controller with the behavior tree of the named template.
+    /// Characters without a usable tree just stand around.
+    pub fn ai(template: &str) -> Self {
+        let root = brain_template(template).unwrap_or(Node::Do(Task::Idle));
+        Controller::AI(Brain::new(root))
     }
 
-    pub fn next_action(&self, obj: &Character ,_gs: &GameState) -> Action {
+    pub fn next_action(&self, obj: &Character ,gs: &GameState) -> Action {
         match self {
             Controller::Dummy => Action::Idle,
             Controller::PlayerController {action} => *action,
-            Controller::AggressiveAI {last_player_pos} => {
-                match last_player_pos {
-                    Some(p) => obj.move_towards(*p),
-                    None    => Action::Idle,
-                }
-            }
+            Controller::AI(brain) => brain.next_action(obj, gs),
         }
     }
 
     pub fn update(&mut self, obj: &Character, gs: &GameState) {
-        match self {
-            Controller::AggressiveAI {ref mut last_player_pos} => {
-                let player = gs.get_player();
-                if gs.check_los(obj.pos, player.pos) {
-                    *last_player_pos = Some(player.pos);
-                }
-            }
-            _ => {}
+        if let Controller::AI(ref mut brain) = self {
+            brain.update(obj, gs);
         }
     }
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, PartialEq, Debug)]
 pub struct StatBlock {
-    str: i32,
-    agi: i32,
-    con: i32,
+    pub str: i32,
+    pub agi: i32,
+    pub con: i32,
+}
+
+impl StatBlock {
+    pub fn new(str: i32, agi: i32, con: i32) -> Self {
+        StatBlock { str, agi, con }
+    }
+
+    pub fn zero() -> Self {
+        StatBlock::new(0, 0, 0)
+    }
+}
+
+impl Add for StatBlock {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        StatBlock {
+            str: self.str + other.str,
+            agi: self.agi + other.agi,
+            con: self.con + other.con,
+        }
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -86,9 +163,16 @@ pub struct Character {
     pub faction: Faction,
     pub alive: bool,
     pub blocking: bool,
+    pub sight_radius: i32,
+    pub sneaking: bool,
+    pub energy: i32,
     pub stat_block: StatBlock,
+    /// Verb used in attack messages when fighting without a weapon
+    pub attack_verb: &'static str,
     pub controller: Box<Controller>,
     pub inventory: Inventory,
+    pub equipment: Equipment,
+    pub statuses: Vec<StatusEffect>,
 }
 
 impl Character {
@@ -110,11 +194,17 @@ impl Character {
                 agi: 10,
                 con: 10,
             },
+            attack_verb: "hits",
             faction: faction,
             alive: true,
             blocking: true,
+            sight_radius: 8,
+            sneaking: false,
+            energy: 0,
             controller: Box::new(Controller::Dummy),
             inventory: Inventory::new(),
+            equipment: Equipment::new(),
+            statuses: vec![],
         }
     }
 
@@ -122,6 +212,16 @@ impl Character {
         let mut o = Character::new(pos, '@', WHITE, "player", Faction::Player);
         o.health = o.max_health();
         o.faction = Faction::Player;
+        o.sight_radius = 32;
+        // The player gets to make the first move
+        o.energy = ACTION_ENERGY;
+        o.equipment.equip(Item::pistol());
+        for _ in 0..6 {
+            o.inventory.add_item(Box::new(Item::pistol_round()));
+        }
+        for _ in 0..2 {
+            o.inventory.add_item(Box::new(Item::throwing_knife()));
+        }
         o.controller = Box::new(Controller::player_controller());
         o
     }
@@ -129,36 +229,162 @@ impl Character {
     pub fn wolf(pos: Pos) -> Character {
         let mut o = Character::new(pos, 'w', DARK_GREY, "grey wolf", Faction::Wolves);
         o.health = o.max_health();
-        o.controller = Box::new(Controller::aggressive_ai());
+        o.controller = Box::new(Controller::ai("wolf"));
+        o.attack_verb = "bites";
+        o.sight_radius = 12;
         o.stat_block = StatBlock {
             str: 6,
-            agi: 8,
+            agi: 14,
             con: 6,
         };
         o
     }
 
-    pub fn healing_potion(pos: Pos) -> Character {
-        let mut o = Character::new(pos, '!', DARK_RED, "healing potion", Faction::Neutral);
-        o.health = 1;
-        o.controller = Box::new(Controller::Dummy);
-        o.stat_block = StatBlock {
-            str: 0,
-            agi: 0,
-            con: 0
-        };
+    pub fn raider(pos: Pos) -> Character {
+        let mut o = Character::new(pos, 'r', DARK_ORANGE, "raider", Faction::Raiders);
+        o.health = o.max_health();
+        o.controller = Box::new(Controller::ai("raider"));
         o.inventory.add_item(Box::new(Item::healing_potion()));
-        o.alive = false;
-        o.blocking = false;
         o
     }
 
+    /// Stats with the bonuses of the worn equipment and active statuses
+    /// applied.
+    pub fn stats(&self) -> StatBlock {
+        self.statuses
+            .iter()
+            .fold(self.stat_block + self.equipment.bonus(), |acc, x| acc + x.kind.bonus())
+    }
+
+    /// How far the character can currently see.
+    pub fn sight(&self) -> i32 {
+        if self.has_status(StatusKind::Blinded) {
+            1
+        } else {
+            self.sight_radius
+        }
+    }
+
+    pub fn has_status(&self, kind: StatusKind) -> bool {
+        self.statuses.iter().any(|x| x.kind == kind)
+    }
+
+    /// Puts a status on the character, stacking it with the same status if
+    /// it is already active. Returns a message describing what happened.
+    pub fn add_status(&mut self, kind: StatusKind, turns: i32) -> String {
+        if let Some(status) = self.statuses.iter_mut().find(|x| x.kind == kind) {
+            status.stack(turns);
+            return match kind.stacking() {
+                Stacking::Intensify(_) => format!("{}'s {} gets worse.", self.name, kind.name()),
+                _                      => format!("{} is {}.", self.name, kind.name()),
+            };
+        }
+        let status = StatusEffect::new(kind, turns);
+        self.statuses.push(status);
+        status.on_apply(self)
+    }
+
+    /// Applies the effects of statuses for one tick and removes the ones
+    /// that ran out. Returns messages about the statuses that ran out.
+    pub fn tick_statuses(&mut self) -> Vec<String> {
+        for status in self.statuses.clone() {
+            status.on_tick(self);
+        }
+        for status in self.statuses.iter_mut() {
+            status.turns -= 1;
+        }
+        let expired: Vec<StatusEffect> = self.statuses
+            .iter()
+            .filter(|x| x.turns <= 0)
+            .cloned()
+            .collect();
+        self.statuses.retain(|x| x.turns > 0);
+        expired
+            .iter()
+            .map(|x| x.on_expire(self))
+            .collect()
+    }
+
+    /// Rough description of how hurt the character is.
+    pub fn condition(&self) -> &'static str {
+        let ratio = self.health as f32 / self.max_health() as f32;
+        if !self.alive {
+            "dead"
+        } else if ratio >= 1.0 {
+            "unhurt"
+        } else if ratio > 0.66 {
+            "lightly wounded"
+        } else if ratio > 0.33 {
+            "wounded"
+        } else {
+            "badly wounded"
+        }
+    }
+
     pub fn max_health(&self) -> i32 {
-        self.stat_block.con / 2
+        max(1, self.stats().con / 2)
+    }
+
+    /// Extra damage dealt by melee weapons thanks to strength.
+    pub fn damage_bonus(&self) -> i32 {
+        (self.stats().str - 10) / 2
     }
 
     pub fn max_damage(&self) -> i32 {
-        self.stat_block.str / 2
+        match self.equipment.melee_weapon().and_then(|x| x.melee.as_ref()) {
+            Some(w) => max(1, w.damage.max() + self.damage_bonus()),
+            None    => max(1, self.stats().str / 2),
+        }
+    }
+
+    /// Amount of energy gained every tick. Characters with average agility
+    /// act once per tick.
+    pub fn speed(&self) -> i32 {
+        max(10, 100 + (self.stats().agi - 10) * 10)
+    }
+
+    /// Amount of energy this character spends on an action. Sneaking and
+    /// carrying too much make moving slower.
+    pub fn action_cost(&self, action: Action) -> i32 {
+        match action {
+            Action::Move(_) => {
+                let mut cost = action.cost();
+                if self.sneaking {
+                    cost = cost * 3 / 2;
+                }
+                if self.is_burdened() {
+                    cost *= 2;
+                }
+                cost
+            }
+            _ => action.cost(),
+        }
+    }
+
+    /// Weight of the inventory and the worn equipment in kilograms.
+    pub fn carried_weight(&self) -> f32 {
+        self.inventory.weight() + self.equipment.weight()
+    }
+
+    /// How many kilograms the character can carry without slowing down.
+    pub fn carry_capacity(&self) -> f32 {
+        max(0, self.stats().str) as f32 * CARRY_PER_STR
+    }
+
+    /// Carrying more than the capacity makes moving slower.
+    pub fn is_burdened(&self) -> bool {
+        self.carried_weight() > self.carry_capacity()
+    }
+
+    /// Carrying far more than the capacity makes moving impossible.
+    pub fn is_overloaded(&self) -> bool {
+        self.carried_weight() > self.carry_capacity() * OVERLOAD_FACTOR
+    }
+
+    /// Checks whether picking up `item` would leave the character unable to
+    /// move.
+    pub fn can_carry(&self, item: &Item) -> bool {
+        self.carried_weight() + item.total_weight() <= self.carry_capacity() * OVERLOAD_FACTOR
     }
 
     pub fn draw(&self, pos: Pos, con: &mut dyn Console) {
@@ -174,24 +400,105 @@ impl Character {
     }
 
     pub fn update(&mut self, gs: &GameState) {
-        let mut controller = *self.controller;
+        // Take the controller out so it can look at the rest of the character
+        let mut controller = std::mem::replace(&mut *self.controller, Controller::Dummy);
         controller.update(self, gs);
         *self.controller = controller;
     }
 
+    /// How hard this character is to hit
+    pub fn evasion(&self) -> i32 {
+        self.stats().agi
+    }
+
+    /// How much damage is absorbed from hits to location `loc`
+    pub fn armor_at(&self, loc: WearLoc) -> i32 {
+        self.equipment.armor_at(loc)
+    }
+
     pub fn attack(&self, other: &mut Character) -> Message {
-        let damage = self.roll_damage();
-        other.take_damage(damage);
-        let msg = format!(
-            "{} attacks {} for {} damage.", 
-            self.name, other.name, damage);
-        Message::new(msg)
+        let weapon = self.equipment.melee_weapon();
+        let verb = weapon
+            .and_then(|x| x.melee.as_ref())
+            .map_or(self.attack_verb, |w| w.verb.as_str());
+        let (result, loc) = roll_attack(self, self.roll_damage(), other);
+        self.resolve_hit(other, result, loc, verb, weapon.map(|x| x.name.as_str()))
+    }
+
+    /// Hits `other` with a projectile shot or thrown from `weapon`.
+    pub fn ranged_attack(&self, other: &mut Character, weapon: &Item) -> Message {
+        let damage = weapon.ranged.as_ref().map_or(1, |r| r.damage.roll());
+        let verb = match &weapon.ranged {
+            Some(RangedWeapon { ammo: Some(_), .. }) => "shoots",
+            _                                        => "hits",
+        };
+        let (result, loc) = roll_attack(self, damage, other);
+        self.resolve_hit(other, result, loc, verb, Some(&weapon.name))
+    }
+
+    /// Applies the outcome of an attack to `other` and describes it.
+    fn resolve_hit(
+        &self,
+        other: &mut Character,
+        result: AttackResult,
+        loc: WearLoc,
+        verb: &str,
+        weapon: Option<&str>) -> Message {
+
+        let with = weapon.map_or(String::new(), |w| format!(" with {}", w));
+        let msg = match result {
+            AttackResult::Miss => format!(
+                "{} misses {}.",
+                self.name, other.name),
+            AttackResult::Hit(0) => format!(
+                "{} {} {} in the {}{}, but the armor absorbs the blow.",
+                self.name, verb, other.name, loc.name(), with),
+            AttackResult::Hit(damage) => {
+                other.take_damage(damage);
+                format!(
+                    "{} {} {} in the {}{} for {} damage.", 
+                    self.name, verb, other.name, loc.name(), with, damage)
+            }
+            AttackResult::Crit(damage) => {
+                other.take_damage(damage);
+                // Critical hits leave bleeding wounds
+                if other.alive {
+                    other.add_status(StatusKind::Bleeding, CRIT_BLEEDING);
+                }
+                format!(
+                    "{} critically {} {} in the {}{} for {} damage!", 
+                    self.name, verb, other.name, loc.name(), with, damage)
+            }
+        };
+        match result {
+            AttackResult::Hit(damage) | AttackResult::Crit(damage)
+                if damage > 0 && other.faction == Faction::Player =>
+                Message::damage_taken(msg),
+            AttackResult::Crit(_) => Message::combat(msg).with_severity(Severity::Notice),
+            _ => Message::combat(msg),
+        }
+    }
+
+    /// Checks whether there is ammunition for the wielded firearm.
+    pub fn can_shoot(&self) -> bool {
+        self.equipment.firearm()
+            .and_then(|x| x.ranged.as_ref())
+            .and_then(|r| r.ammo.as_ref())
+            .is_some_and(|ammo| self.inventory.find(ammo).is_some())
+    }
+
+    /// Finds the first item in the inventory that is made for throwing.
+    pub fn throwable(&self) -> Option<ItemId> {
+        self.inventory.items
+            .iter()
+            .find(|x| x.is_throwable())
+            .map(|x| x.id)
     }
 
     pub fn take_damage(&mut self, damage: i32) {
         assert!(damage >= 0);
         self.health -= damage;
-        if self.health <= 0 {
+        if self.health <= 0 && self.alive {
             self.die();
         }
     }
@@ -206,12 +513,21 @@ impl Character {
         self.char = '%';
         self.color = DARK_RED;
         self.alive = false;
+        // Everything that was carried or worn is left on the ground next
+        // to the corpse
+        for item in self.equipment.items.drain(..) {
+            self.inventory.add_item(Box::new(item));
+        }
         let corpse = Box::new(self.make_corpse());
         self.inventory.add_item(corpse);
     }
 
     fn make_corpse(&self) -> Item {
-        Item::new(format!("corpse of {}", self.name), format!("It's a corpse."))
+        let mut corpse = Item::new(format!("corpse of {}", self.name), format!("It's a corpse."));
+        corpse.weight = 30.0;
+        corpse.char = '%';
+        corpse.color = DARK_RED;
+        corpse
     }
 
     pub fn next_action(&self, gs: &GameState) -> Action {
@@ -226,8 +542,13 @@ impl Character {
     }
 
     pub fn roll_damage(&self) -> i32 {
-        let rng = Rng::get_instance();
-        rng.get_int(1, self.max_damage())
+        match self.equipment.melee_weapon().and_then(|x| x.melee.as_ref()) {
+            Some(w) => max(1, w.damage.roll() + self.damage_bonus()),
+            None    => {
+                let rng = Rng::get_instance();
+                rng.get_int(1, self.max_damage())
+            }
+        }
     }
 }
 
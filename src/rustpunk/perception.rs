@@ -0,0 +1,70 @@
+use crate::rustpunk::gamestate::GameState;
+use crate::rustpunk::object::Faction;
+use crate::rustpunk::pos::Pos;
+
+use tcod::line::*;
+
+/// How loud a footstep is when walking normally.
+pub const FOOTSTEP_VOLUME: i32 = 4;
+/// How loud a footstep is when sneaking.
+pub const SNEAK_FOOTSTEP_VOLUME: i32 = 1;
+/// How loud a fight is.
+pub const COMBAT_VOLUME: i32 = 12;
+/// How loud a gunshot is.
+pub const GUNSHOT_VOLUME: i32 = 20;
+/// How much volume is lost for each wall between the noise and the listener.
+const WALL_DAMPING: i32 = 3;
+
+/// Number of turns an NPC remembers where it saw a target.
+pub const SIGHT_MEMORY: i32 = 20;
+/// Number of turns an NPC remembers where it heard a target.
+pub const NOISE_MEMORY: i32 = 8;
+
+/// A sound made somewhere on the map during a turn.
+#[derive(Clone, Copy, Debug)]
+pub struct Noise {
+    pub pos: Pos,
+    pub volume: i32,
+    /// Faction of whoever made the noise
+    pub faction: Faction,
+    pub turn: u32,
+}
+
+impl Noise {
+    pub fn new(pos: Pos, volume: i32, faction: Faction, turn: u32) -> Self {
+        Noise {
+            pos: pos,
+            volume: volume,
+            faction: faction,
+            turn: turn,
+        }
+    }
+
+    /// Checks whether the noise can be heard at position `pos`. Walls between
+    /// the noise and the listener muffle the sound.
+    pub fn is_audible(&self, gs: &GameState, pos: Pos) -> bool {
+        if self.pos.distance(pos) > self.volume {
+            return false;
+        }
+        let walls = Line::new(self.pos.tup(), pos.tup())
+            .filter(|(x, y)| gs.is_opaque(Pos::new(*x, *y)))
+            .count() as i32;
+        self.pos.distance(pos) + walls * WALL_DAMPING <= self.volume
+    }
+}
+
+/// A remembered position that fades after a number of turns.
+#[derive(Clone, Copy, Debug)]
+pub struct Memory {
+    pub pos: Pos,
+    pub turns_left: i32,
+}
+
+impl Memory {
+    pub fn new(pos: Pos, turns: i32) -> Self {
+        Memory {
+            pos: pos,
+            turns_left: turns,
+        }
+    }
+}
@@ -4,7 +4,7 @@ use std::ops::Add;
 use std::ops::AddAssign;
 use std::ops::Sub;
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub struct Pos {
     pub x: i32,
     pub y: i32,
@@ -23,6 +23,11 @@ impl Pos {
         (self.x, self.y)
     }
 
+    /// Manhattan distance to another position
+    pub fn distance(self, other: Pos) -> i32 {
+        abs(self.x - other.x) + abs(self.y - other.y)
+    }
+
     pub fn to_dir(self) -> Option<Dir> {
         match self.tup() {
             ( 0, -1) => Some(Dir::N),
@@ -0,0 +1,208 @@
+use tcod::colors::*;
+use tcod::console::*;
+
+use crate::rustpunk::gamestate::*;
+use crate::rustpunk::message::*;
+use crate::rustpunk::pos::*;
+use crate::rustpunk::view::*;
+use crate::rustpunk::layout::*;
+use crate::rustpunk::menu::*;
+
+const MIN_MESSAGE_CAP: usize = 100;
+const MAX_MESSAGE_CAP: usize = 2000;
+const MESSAGE_CAP_STEP: usize = 100;
+
+/// What the whole window is showing. Views only exist while playing.
+pub enum Screen {
+    Title(TitleScreen),
+    Options(OptionsScreen),
+    Playing,
+    GameOver(GameOverScreen),
+}
+
+/// Settings that outlive a single game.
+#[derive(Clone, Copy, Debug)]
+pub struct Options {
+    /// How many messages the history keeps
+    pub message_cap: usize,
+}
+
+impl Options {
+    pub fn new() -> Self {
+        Options {
+            message_cap: DEFAULT_MESSAGE_CAP,
+        }
+    }
+}
+
+fn clear_screen(con: &mut Offscreen, title: &str) {
+    con.set_default_background(BLACK);
+    con.set_default_foreground(LIGHT_BLUE);
+    con.clear();
+    con.print_ex(
+        SCREEN_WIDTH/2,
+        SCREEN_HEIGHT/4,
+        BackgroundFlag::None,
+        TextAlignment::Center,
+        title);
+}
+
+const MENU_WIDTH: i32 = 30;
+
+/// Area of a menu with `rows` entries in the middle of the screen.
+fn menu_area(rows: i32) -> Rect {
+    Rect { x: (SCREEN_WIDTH - MENU_WIDTH)/2, y: SCREEN_HEIGHT/2, w: MENU_WIDTH, h: rows }
+}
+
+pub struct TitleScreen {
+    menu: Menu<Command>,
+    /// Whether there is a game in progress to go back to
+    can_continue: bool,
+}
+
+impl TitleScreen {
+    pub fn new(can_continue: bool) -> Self {
+        let entries = vec![
+            MenuEntry::new("New Game".to_string(), Command::NewGame),
+            MenuEntry::new("Continue".to_string(), Command::Continue).enabled(can_continue),
+            MenuEntry::new("Options".to_string(), Command::Options),
+            MenuEntry::new("Quit".to_string(), Command::ExitGame),
+        ];
+        TitleScreen {
+            menu: Menu::new(entries, menu_area(4)),
+            can_continue: can_continue,
+        }
+    }
+
+    pub fn handle_command(&mut self, com: Command) -> Option<Command> {
+        match self.menu.handle_command(com) {
+            MenuEvent::Chosen(c) => Some(c),
+            MenuEvent::Ignored   => match com {
+                Command::CloseView if self.can_continue => Some(Command::Continue),
+                _                                       => None,
+            },
+            MenuEvent::Moved     => None,
+        }
+    }
+
+    pub fn render(&self, con: &mut Offscreen) {
+        clear_screen(con, "V A N A P A G A N");
+        self.menu.render(con, true);
+    }
+}
+
+/// Entries of the options screen.
+#[derive(Clone, Copy, PartialEq)]
+enum OptionsEntry {
+    MessageCap,
+    Back,
+}
+
+pub struct OptionsScreen {
+    menu: Menu<OptionsEntry>,
+}
+
+impl OptionsScreen {
+    pub fn new(options: &Options) -> Self {
+        let mut v = OptionsScreen {
+            menu: Menu::new(vec![], menu_area(2)),
+        };
+        v.update_entries(options);
+        v
+    }
+
+    fn update_entries(&mut self, options: &Options) {
+        self.menu.set_entries(vec![
+            MenuEntry::new(
+                format!("Message history: < {} >", options.message_cap),
+                OptionsEntry::MessageCap),
+            MenuEntry::new("Back".to_string(), OptionsEntry::Back),
+        ]);
+    }
+
+    /// Changes the settings in `options`. Returns `Command::MainMenu` when
+    /// the player is done.
+    pub fn handle_command(&mut self, options: &mut Options, com: Command) -> Option<Command> {
+        match self.menu.handle_command(com) {
+            MenuEvent::Chosen(OptionsEntry::Back) => return Some(Command::MainMenu),
+            MenuEvent::Chosen(_) | MenuEvent::Moved => return None,
+            MenuEvent::Ignored => {}
+        }
+        let on_cap = self.menu.selected() == Some(OptionsEntry::MessageCap);
+        match com {
+            Command::Move(Dir::W) if on_cap => {
+                options.message_cap = MIN_MESSAGE_CAP.max(options.message_cap - MESSAGE_CAP_STEP);
+            }
+            Command::Move(Dir::E) if on_cap => {
+                options.message_cap = MAX_MESSAGE_CAP.min(options.message_cap + MESSAGE_CAP_STEP);
+            }
+            Command::CloseView => return Some(Command::MainMenu),
+            _ => {}
+        }
+        self.update_entries(options);
+        None
+    }
+
+    pub fn render(&self, con: &mut Offscreen) {
+        clear_screen(con, "Options");
+        self.menu.render(con, true);
+        con.print_ex(
+            SCREEN_WIDTH/2,
+            SCREEN_HEIGHT-3,
+            BackgroundFlag::None,
+            TextAlignment::Center,
+            "Left/right: change  Esc: back");
+    }
+}
+
+/// Shown after the player has died.
+pub struct GameOverScreen {
+    cause: String,
+    lines: Vec<String>,
+}
+
+impl GameOverScreen {
+    pub fn new(state: &GameState) -> Self {
+        let stats = state.statistics();
+        let cause = stats.cause_of_death
+            .clone()
+            .unwrap_or("Died under mysterious circumstances".to_string());
+        GameOverScreen {
+            cause: cause,
+            lines: vec![
+                format!("Turns survived: {}", state.turn()),
+                format!("Depth reached:  {}", state.depth()),
+                format!("Enemies killed: {}", stats.kills),
+                format!("Damage dealt:   {}", stats.damage_dealt),
+            ],
+        }
+    }
+
+    pub fn handle_command(&mut self, com: Command) -> Option<Command> {
+        match com {
+            Command::Select | Command::CloseView => Some(Command::MainMenu),
+            _                                    => None,
+        }
+    }
+
+    pub fn render(&self, con: &mut Offscreen) {
+        clear_screen(con, "You have died");
+        con.set_default_foreground(LIGHT_RED);
+        con.print_ex(
+            SCREEN_WIDTH/2,
+            SCREEN_HEIGHT/4 + 2,
+            BackgroundFlag::None,
+            TextAlignment::Center,
+            &self.cause);
+        con.set_default_foreground(WHITE);
+        for (i, line) in self.lines.iter().enumerate() {
+            con.print(SCREEN_WIDTH/2 - 10, SCREEN_HEIGHT/2 + i as i32, line);
+        }
+        con.print_ex(
+            SCREEN_WIDTH/2,
+            SCREEN_HEIGHT-3,
+            BackgroundFlag::None,
+            TextAlignment::Center,
+            "Press Enter to return to the main menu");
+    }
+}
@@ -0,0 +1,159 @@
+use crate::rustpunk::object::Character;
+use crate::rustpunk::object::StatBlock;
+
+use tcod::colors::*;
+
+/// Temporary conditions that can affect a character.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum StatusKind {
+    /// Loses health every tick, more with every dose
+    Poisoned,
+    /// Moves and fights faster, but crashes afterwards
+    Stimmed,
+    /// Can see only right next to itself
+    Blinded,
+    /// Stats are changed by the given amounts
+    Buffed(StatBlock),
+    /// Loses health every tick, more with every wound
+    Bleeding,
+    /// Doesn't gain energy, so can't act
+    Stunned,
+    /// Loses a lot of health every tick
+    Burning,
+    /// Cyberware is scrambled, making the character weak and clumsy
+    Hacked,
+}
+
+/// What happens when a status is applied to a character that already has it.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Stacking {
+    /// The duration is reset to the longer of the two
+    Refresh,
+    /// The durations are added together
+    Extend,
+    /// The duration is refreshed and the status gets stronger, up to the
+    /// given number of stacks
+    Intensify(i32),
+}
+
+impl StatusKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            StatusKind::Poisoned  => "poisoned",
+            StatusKind::Stimmed   => "stimmed",
+            StatusKind::Blinded   => "blinded",
+            StatusKind::Buffed(_) => "buffed",
+            StatusKind::Bleeding  => "bleeding",
+            StatusKind::Stunned   => "stunned",
+            StatusKind::Burning   => "burning",
+            StatusKind::Hacked    => "hacked",
+        }
+    }
+
+    /// Character and color of the icon shown in the GUI.
+    pub fn icon(self) -> (char, Color) {
+        match self {
+            StatusKind::Poisoned  => ('p', GREEN),
+            StatusKind::Stimmed   => ('+', LIGHT_BLUE),
+            StatusKind::Blinded   => ('b', LIGHT_GREY),
+            StatusKind::Buffed(_) => ('^', YELLOW),
+            StatusKind::Bleeding  => ('*', RED),
+            StatusKind::Stunned   => ('z', LIGHT_YELLOW),
+            StatusKind::Burning   => ('&', ORANGE),
+            StatusKind::Hacked    => ('%', CYAN),
+        }
+    }
+
+    pub fn stacking(self) -> Stacking {
+        match self {
+            StatusKind::Poisoned  => Stacking::Intensify(3),
+            StatusKind::Bleeding  => Stacking::Intensify(5),
+            StatusKind::Buffed(_) => Stacking::Extend,
+            _                     => Stacking::Refresh,
+        }
+    }
+
+    /// Stat changes while the status is active.
+    pub fn bonus(self) -> StatBlock {
+        match self {
+            StatusKind::Stimmed   => StatBlock::new(1, 3, 0),
+            StatusKind::Buffed(b) => b,
+            StatusKind::Hacked    => StatBlock::new(-2, -2, 0),
+            _                     => StatBlock::zero(),
+        }
+    }
+}
+
+/// A status together with the number of ticks it still lasts.
+#[derive(Clone, Copy, Debug)]
+pub struct StatusEffect {
+    pub kind: StatusKind,
+    pub turns: i32,
+    /// How many times the status has been stacked on top of itself
+    pub stacks: i32,
+}
+
+impl StatusEffect {
+    pub fn new(kind: StatusKind, turns: i32) -> Self {
+        StatusEffect {
+            kind: kind,
+            turns: turns,
+            stacks: 1,
+        }
+    }
+
+    /// Merges another application of the same status into this one.
+    pub fn stack(&mut self, turns: i32) {
+        match self.kind.stacking() {
+            Stacking::Refresh => {
+                self.turns = self.turns.max(turns);
+            }
+            Stacking::Extend => {
+                self.turns += turns;
+            }
+            Stacking::Intensify(max_stacks) => {
+                self.turns = self.turns.max(turns);
+                self.stacks = (self.stacks + 1).min(max_stacks);
+            }
+        }
+    }
+
+    /// Called when the status is first put on a character. Returns a message
+    /// describing what happened.
+    pub fn on_apply(&self, ch: &mut Character) -> String {
+        match self.kind {
+            StatusKind::Stunned => {
+                // Whatever the character was about to do is lost
+                ch.energy = ch.energy.min(0);
+                format!("{} is stunned.", ch.name)
+            }
+            StatusKind::Burning  => format!("{} catches fire!", ch.name),
+            StatusKind::Bleeding => format!("{} starts bleeding.", ch.name),
+            kind                 => format!("{} is {}.", ch.name, kind.name()),
+        }
+    }
+
+    /// Called every tick while the status is active.
+    pub fn on_tick(&self, ch: &mut Character) {
+        match self.kind {
+            StatusKind::Poisoned | StatusKind::Bleeding => ch.take_damage(self.stacks),
+            StatusKind::Burning                         => ch.take_damage(2),
+            _                                           => {}
+        }
+    }
+
+    /// Called when the status runs out. Returns a message describing what
+    /// happened.
+    pub fn on_expire(&self, ch: &mut Character) -> String {
+        match self.kind {
+            StatusKind::Stimmed => {
+                // Coming down from stims knocks you out for a moment
+                let msg = format!("{} crashes from the stims.", ch.name);
+                ch.add_status(StatusKind::Stunned, 2);
+                msg
+            }
+            StatusKind::Burning => format!("The flames on {} die out.", ch.name),
+            kind                => format!("{} is no longer {}.", ch.name, kind.name()),
+        }
+    }
+}
@@ -6,6 +6,7 @@ use crate::rustpunk::pos::Pos;
 #[derive(Clone, Copy, Debug)]
 /// Structure for storing information about tiles
 pub struct Tile {
+    pub name: &'static str,
     pub solid: bool,
     pub opaque: bool,
     pub char: char,
@@ -17,6 +18,7 @@ impl Tile {
     /// Default empty tile
     pub fn empty() -> Self {
         Tile {
+            name: "floor",
             solid: false,
             opaque: false,
             char: '.',
@@ -28,6 +30,7 @@ impl Tile {
     /// Default wall tile
     pub fn wall() -> Self {
         Tile {
+            name: "wall",
             solid: true,
             opaque: true,
             char: '#',
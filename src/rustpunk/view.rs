@@ -1,67 +1,365 @@
 use core::cmp::*;
+use tcod::colors::*;
 use tcod::console::*;
 
 use crate::rustpunk::gamestate::*;
 use crate::rustpunk::item::*;
 use crate::rustpunk::pos::*;
 use crate::rustpunk::object::*;
+use crate::rustpunk::message::*;
+use crate::rustpunk::layout::*;
+use crate::rustpunk::combat::*;
+use crate::rustpunk::menu::*;
 
-pub const SCREEN_WIDTH: i32 = 80;
-pub const SCREEN_HEIGHT: i32 = 50;
 pub const MENU_MARGIN: i32 = 5;
+const ACTION_MENU_WIDTH: i32 = 16;
+const ACTION_MENU_HEIGHT: i32 = 10;
+/// Lines the message log moves for every step of the mouse wheel
+const WHEEL_LINES: i32 = 3;
+/// Evasion of a defender with average agility. The character sheet shows
+/// the hit chance against it.
+const AVERAGE_EVASION: i32 = 10;
 
+#[derive(Clone, Copy)]
 pub enum Command {
     Move(Dir),
     Select,
     GetItem,
     Wait,
+    Sneak,
     ExitGame,
     CloseView,
     OpenInventory,
+    Fire,
+    Equip,
+    /// Open a targeting view
+    Target(Aim),
+    /// Carry out the aim at the position chosen in a targeting view
+    Aimed(Aim, Pos),
+    /// Open the container lying under the player
+    Open,
+    /// Open a transfer view for the container with the given id
+    OpenContainer(ItemId),
+    /// Mark or unmark a list entry
+    Mark,
+    /// Drop the selected items
+    Drop,
+    /// Examine the selected item
+    Examine,
+    /// Open the examine view for the item with the given id
+    ShowItem(ItemId),
+    /// Look around the map with a cursor
+    Look,
+    /// Open the message history
+    MessageLog,
+    /// Show the player's character sheet
+    CharacterSheet,
+    /// Start a fresh game
+    NewGame,
+    /// Go back to the game in progress
+    Continue,
+    /// Open the options screen
+    Options,
+    /// Leave the game for the main menu
+    MainMenu,
+    /// Close the view after it has carried out its action
+    Finish,
+    /// Choose the menu entry with the given letter
+    Hotkey(char),
+    PageUp,
+    PageDown,
+    /// Mouse wheel
+    ScrollUp,
+    ScrollDown,
+    /// Left mouse click at the given screen position
+    Click(Pos),
 }
 
+/// A screen that takes over the input. Views are kept on a stack: a view
+/// can open children on top of itself, and it is told when they close.
 pub trait View {
     fn handle_command(&mut self, state: &mut GameState, com: Command) -> Option<Command>;
     fn render(&self, con: &mut Offscreen);
+
+    /// Called when the child view on top of this one is closed. `result` is
+    /// what the child handed back, or `None` if it was cancelled. The
+    /// returned command is handled as if this view had returned it.
+    fn child_closed(&mut self, _state: &mut GameState, _result: Option<Command>) -> Option<Command> {
+        None
+    }
+
+    /// What the view hands back to its parent after closing with
+    /// `Command::Finish`.
+    fn result(&self) -> Option<Command> {
+        None
+    }
+
+    /// Whether the view is drawn on top of the map rather than on top of
+    /// the views below it.
+    fn shows_map(&self) -> bool {
+        false
+    }
+
+    /// Whether letter keys choose menu entries instead of issuing their
+    /// usual commands. The usual commands are then given with shift.
+    fn accepts_hotkeys(&self) -> bool {
+        false
+    }
+}
+
+/// Something that can be done with an item from the inventory.
+#[derive(Clone, Copy, PartialEq)]
+pub enum ItemAction {
+    Use,
+    Open,
+    Equip,
+    Unequip,
+    Throw,
+    Drop,
+    Examine,
+}
+
+impl ItemAction {
+    pub fn name(self) -> &'static str {
+        match self {
+            ItemAction::Use     => "Use",
+            ItemAction::Open    => "Open",
+            ItemAction::Equip   => "Equip",
+            ItemAction::Unequip => "Take off",
+            ItemAction::Throw   => "Throw",
+            ItemAction::Drop    => "Drop",
+            ItemAction::Examine => "Examine",
+        }
+    }
+}
+
+/// Actions that make sense for `item`. Worn items have to be taken off
+/// before anything else can be done with them.
+fn item_actions(item: &Item, worn: bool) -> Vec<ItemAction> {
+    if worn {
+        return vec![ItemAction::Unequip, ItemAction::Examine];
+    }
+    let mut actions = vec![];
+    if !item.on_use.is_empty() {
+        actions.push(ItemAction::Use);
+    }
+    if item.contents.is_some() {
+        actions.push(ItemAction::Open);
+    }
+    if !item.wearable.is_empty() {
+        actions.push(ItemAction::Equip);
+    }
+    if item.is_throwable() {
+        actions.push(ItemAction::Throw);
+    }
+    actions.push(ItemAction::Drop);
+    actions.push(ItemAction::Examine);
+    actions
 }
 
 pub struct InventoryView {
-    items: Vec<String>,
-    cursor: i32,
+    /// Worn items followed by carried ones. The flag tells whether the item
+    /// is worn.
+    list: Menu<(ItemId, bool)>,
+    /// Description of how much the player is carrying
+    load: String,
+    /// Items marked for dropping
+    marked: Vec<ItemId>,
+    /// Actions offered for the item under the cursor, if the action menu
+    /// is open
+    actions: Option<Menu<ItemAction>>,
 }
 
 impl InventoryView {
-    pub fn new(inventory: &Inventory) -> Self {
+    pub fn new(player: &Character) -> Self {
         let mut v = InventoryView {
-            items: vec![],
-            cursor: 0,
+            list: Menu::new(vec![], Rect {
+                x: MENU_MARGIN+2,
+                y: MENU_MARGIN+2,
+                w: SCREEN_WIDTH-MENU_MARGIN*2-4,
+                h: SCREEN_HEIGHT-MENU_MARGIN*2-6,
+            }),
+            load: String::new(),
+            marked: vec![],
+            actions: None,
         };
-        v.update_items(inventory);
+        v.update_items(player);
         v
     }
 
-    pub fn update_items(&mut self, inventory: &Inventory)  {
-        self.items =  inventory.items.iter().map(|x| x.name.clone()).collect();
+    pub fn update_items(&mut self, player: &Character)  {
+        self.marked.retain(|id| player.inventory.get_item(*id).is_some());
+        let mut entries = vec![];
+        if !player.equipment.items.is_empty() {
+            entries.push(MenuEntry::header("Worn".to_string()));
+        }
+        for item in player.equipment.items.iter() {
+            let locs: Vec<&str> = item.wearable.iter().map(|l| l.name()).collect();
+            let text = format!("{} ({})", item.name, locs.join(", "));
+            entries.push(MenuEntry::new(text, (item.id, true)));
+        }
+        entries.push(MenuEntry::header("Carried".to_string()));
+        for item in player.inventory.items.iter() {
+            let entry = MenuEntry::new(item.display_name(), (item.id, false));
+            entries.push(entry.marked(self.marked.contains(&item.id)));
+        }
+        self.list.set_entries(entries);
+        let condition = if player.is_overloaded() {
+            " (overloaded)"
+        } else if player.is_burdened() {
+            " (burdened)"
+        } else {
+            ""
+        };
+        self.load = format!(
+            "Carrying {:.1}/{:.1} kg{}",
+            player.carried_weight(), player.carry_capacity(), condition);
+    }
+
+    /// Id of the item under the cursor and whether it is worn.
+    fn selected(&self) -> Option<(ItemId, bool)> {
+        self.list.selected()
+    }
+
+    /// Drops the whole stack with id `id` on the floor.
+    fn drop_stack(&self, state: &mut GameState, id: ItemId) {
+        let count = state.get_player().inventory.get_item(id).map(|x| x.count);
+        if let Some(count) = count {
+            state.player_action(Action::DropItem(id, count));
+        }
+    }
+
+    /// Carries out `action` on the item with id `id`.
+    fn perform(&mut self, state: &mut GameState, action: ItemAction, id: ItemId) -> Option<Command> {
+        match action {
+            ItemAction::Use => {
+                let targeted = state.get_player()
+                    .inventory
+                    .get_item(id)
+                    .is_some_and(|x| x.targeted);
+                if targeted {
+                    return Some(Command::Target(Aim::Use(id)));
+                }
+                state.player_action(Action::UseItem(id));
+            }
+            ItemAction::Open    => return Some(Command::OpenContainer(id)),
+            ItemAction::Equip   => state.player_action(Action::Equip(id)),
+            ItemAction::Unequip => state.player_action(Action::Unequip(id)),
+            ItemAction::Throw   => return Some(Command::Target(Aim::Throw(id))),
+            ItemAction::Drop    => self.drop_stack(state, id),
+            ItemAction::Examine => return Some(Command::ShowItem(id)),
+        }
+        None
+    }
+
+    /// Opens the action menu for the item with id `id`.
+    fn open_actions(&mut self, state: &GameState, id: ItemId, worn: bool) {
+        let player = state.get_player();
+        let item = player.inventory
+            .get_item(id)
+            .or(player.equipment.items.iter().find(|x| x.id == id));
+        if let Some(item) = item {
+            let entries: Vec<MenuEntry<ItemAction>> = item_actions(item, worn)
+                .into_iter()
+                .map(|x| MenuEntry::new(x.name().to_string(), x))
+                .collect();
+            let rows = min(entries.len() as i32, ACTION_MENU_HEIGHT);
+            // Next to the item, but never hanging over the bottom of the frame
+            let y = min(
+                MENU_MARGIN+2+self.list.cursor_row(),
+                SCREEN_HEIGHT-MENU_MARGIN-rows-2);
+            let area = Rect { x: SCREEN_WIDTH/2+1, y: y+1, w: ACTION_MENU_WIDTH-2, h: rows };
+            self.actions = Some(Menu::new(entries, area));
+        }
+    }
+
+    fn handle_menu_command(&mut self, state: &mut GameState, com: Command) -> Option<Command> {
+        let event = match self.actions {
+            Some(ref mut actions) => actions.handle_command(com),
+            None                  => return None,
+        };
+        match event {
+            MenuEvent::Chosen(action) => {
+                self.actions = None;
+                if let Some((id, _)) = self.selected() {
+                    let result = self.perform(state, action, id);
+                    self.update_items(&state.get_player());
+                    return result;
+                }
+            }
+            MenuEvent::Ignored => if let Command::CloseView = com {
+                self.actions = None;
+            },
+            MenuEvent::Moved => {}
+        }
+        None
     }
 }
 
 impl View for InventoryView {
     fn handle_command(&mut self, state: &mut GameState, com: Command) -> Option<Command>{
+        if self.actions.is_some() {
+            return self.handle_menu_command(state, com);
+        }
+        match self.list.handle_command(com) {
+            MenuEvent::Chosen((id, worn)) => {
+                self.open_actions(state, id, worn);
+                return None;
+            }
+            MenuEvent::Moved   => return None,
+            MenuEvent::Ignored => {}
+        }
         match com {
-            Command::Move(Dir::N) => self.cursor = max(0, self.cursor-1),
-            Command::Move(Dir::S) => self.cursor = min(
-                self.items.len() as i32-1, 
-                self.cursor+1),
             Command::CloseView => return Some(Command::CloseView),
-            Command::Select => if self.items.len() > 0 {
-                state.use_item(0, self.cursor);
-                self.update_items(&state.get_player().inventory);
+            Command::Mark => if let Some((id, false)) = self.selected() {
+                if self.marked.contains(&id) {
+                    self.marked.retain(|x| *x != id);
+                } else {
+                    self.marked.push(id);
+                }
+                self.update_items(&state.get_player());
+            },
+            Command::Drop => {
+                if !self.marked.is_empty() {
+                    for id in self.marked.clone() {
+                        self.drop_stack(state, id);
+                    }
+                    self.marked.clear();
+                } else if let Some((id, false)) = self.selected() {
+                    self.drop_stack(state, id);
+                }
+                self.update_items(&state.get_player());
+            }
+            Command::Examine => if let Some((id, _)) = self.selected() {
+                return Some(Command::ShowItem(id));
+            },
+            Command::Equip => if let Some((id, worn)) = self.selected() {
+                if worn {
+                    state.player_action(Action::Unequip(id));
+                } else {
+                    state.player_action(Action::Equip(id));
+                }
+                self.update_items(&state.get_player());
             },
             _ => {}
         }
         None
     }
 
+    fn child_closed(&mut self, state: &mut GameState, result: Option<Command>) -> Option<Command> {
+        // Back to the map once an item has been thrown or used on something
+        if let Some(Command::Aimed(aim, target)) = result {
+            state.player_action(aim.action(target));
+            return Some(Command::CloseView);
+        }
+        self.update_items(&state.get_player());
+        None
+    }
+
+    fn accepts_hotkeys(&self) -> bool {
+        true
+    }
+
     fn render(&self, con: &mut Offscreen) {
         con.print_frame(
             MENU_MARGIN, 
@@ -71,92 +369,264 @@ impl View for InventoryView {
             true,
             BackgroundFlag::Set,
             Some("Inventory"));
-        for i in 0..self.items.len() {
-            con.print(MENU_MARGIN+4, MENU_MARGIN+2+i as i32, &self.items[i]);
+        self.list.render(con, self.actions.is_none());
+        con.print(
+            MENU_MARGIN+4,
+            SCREEN_HEIGHT-MENU_MARGIN-3,
+            "Enter: actions  Space: mark  D: drop  E: equip  X: examine");
+        con.print(MENU_MARGIN+4, SCREEN_HEIGHT-MENU_MARGIN-2, &self.load);
+        if let Some(ref actions) = self.actions {
+            let area = actions.area();
+            con.print_frame(
+                area.x-1,
+                area.y-1,
+                area.w+2,
+                area.h+2,
+                true,
+                BackgroundFlag::Set,
+                None::<&str>);
+            actions.render(con, true);
+        }
+    }
+}
+
+/// Shows everything there is to know about a single item.
+pub struct ExamineView {
+    title: String,
+    /// Paragraphs of text, word-wrapped to fit the frame
+    paragraphs: Vec<String>,
+}
+
+impl ExamineView {
+    pub fn new(item: &Item) -> Self {
+        let mut paragraphs = vec![item.description.clone(), String::new()];
+        for effect in item.on_use.iter().filter_map(|x| x.describe()) {
+            paragraphs.push(format!("When used: {}.", effect));
+        }
+        if !item.wearable.is_empty() {
+            let locs: Vec<&str> = item.wearable.iter().map(|x| x.name()).collect();
+            paragraphs.push(format!("Worn on: {}", locs.join(", ")));
+        }
+        if let Some(r) = &item.ranged {
+            let ammo = r.ammo.as_ref().map_or(String::new(), |x| format!(", fires {}", x));
+            paragraphs.push(format!("Ranged: {} damage, range {}{}", r.damage, r.range, ammo));
+        }
+        if let Some(m) = &item.melee {
+            paragraphs.push(format!("Melee: {} damage", m.damage));
+        }
+        if item.armor != 0 {
+            paragraphs.push(format!("Armor: {}", item.armor));
+        }
+        if item.bonus != StatBlock::zero() {
+            paragraphs.push(format!("Bonus: {}", describe_bonus(item.bonus)));
+        }
+        if let Some(contents) = &item.contents {
+            paragraphs.push(format!("Holds {} items", contents.items.len()));
+        }
+        if let Some(lock) = &item.lock {
+            paragraphs.push(format!("Locked, opens with a {}", lock));
+        }
+        if item.count > 1 {
+            paragraphs.push(format!(
+                "Weight: {:.2} kg each, {:.2} kg in total",
+                item.weight, item.total_weight()));
+            paragraphs.push(format!(
+                "Value: {} credits each, {} in total",
+                item.value, item.value * item.count));
+        } else {
+            paragraphs.push(format!("Weight: {:.2} kg", item.total_weight()));
+            paragraphs.push(format!("Value: {} credits", item.value));
+        }
+        ExamineView {
+            title: item.display_name(),
+            paragraphs: paragraphs,
+        }
+    }
+}
+
+impl View for ExamineView {
+    fn handle_command(&mut self, _state: &mut GameState, com: Command) -> Option<Command> {
+        match com {
+            Command::CloseView | Command::Select => Some(Command::CloseView),
+            _                                    => None,
         }
-        con.put_char(
-            MENU_MARGIN+2, 
-            MENU_MARGIN+2+self.cursor as i32, 
-            '>', 
-            BackgroundFlag::Set);
     }
+
+    fn render(&self, con: &mut Offscreen) {
+        con.print_frame(
+            MENU_MARGIN, 
+            MENU_MARGIN,
+            SCREEN_WIDTH-MENU_MARGIN*2,
+            SCREEN_HEIGHT-MENU_MARGIN*2,
+            true,
+            BackgroundFlag::Set,
+            Some(&self.title));
+        let width = SCREEN_WIDTH - MENU_MARGIN*2 - 6;
+        let mut y = MENU_MARGIN+2;
+        for text in self.paragraphs.iter() {
+            con.print_rect(MENU_MARGIN+3, y, width, 0, text);
+            y += max(1, con.get_height_rect(MENU_MARGIN+3, y, width, 0, text));
+        }
+    }
+}
+
+/// Lists the non-zero stat changes, e.g. "str +2, agi -1".
+fn describe_bonus(bonus: StatBlock) -> String {
+    let stats = [("str", bonus.str), ("agi", bonus.agi), ("con", bonus.con)];
+    let parts: Vec<String> = stats
+        .iter()
+        .filter(|(_, x)| *x != 0)
+        .map(|(name, x)| format!("{} {:+}", name, x))
+        .collect();
+    parts.join(", ")
 }
 
 pub struct PickupView {
-    player_items: Vec<String>,
-    player_cursor: i32,
-    other_items: Vec<String>,
-    other_cursor: i32,
-    other_idx: i32,
+    player_items: Menu<ItemId>,
+    other_items: Menu<ItemId>,
     in_player_col: bool,
-    active: bool,
+    /// Container whose contents are shown in the right column, or `None`
+    /// for the items on the ground
+    container: Option<ItemId>,
+    /// Heading of the right column
+    other_title: String,
 }
 
 impl PickupView {
 
-    pub fn new(state: &GameState, other_idx: i32) -> Self {
-        PickupView {
-            player_items: inv_to_list(&state.get_player().inventory),
-            player_cursor: 0,
-            other_items: inv_to_list(&state.get_object(other_idx as usize).inventory),
-            other_cursor: 0,
-            other_idx: other_idx,
+    pub fn new(state: &GameState) -> Self {
+        PickupView::with_source(state, None, "Ground".to_string())
+    }
+
+    /// Creates a view for moving items between the player's inventory and
+    /// the container with id `id`.
+    pub fn container(state: &GameState, id: ItemId, name: String) -> Self {
+        PickupView::with_source(state, Some(id), name)
+    }
+
+    fn with_source(state: &GameState, container: Option<ItemId>, other_title: String) -> Self {
+        let width = SCREEN_WIDTH/2 - MENU_MARGIN - 4;
+        let height = SCREEN_HEIGHT - MENU_MARGIN*2 - 7;
+        let mut v = PickupView {
+            player_items: Menu::new(vec![], Rect {
+                x: MENU_MARGIN+2,
+                y: MENU_MARGIN+4,
+                w: width,
+                h: height,
+            }),
+            other_items: Menu::new(vec![], Rect {
+                x: SCREEN_WIDTH/2+2,
+                y: MENU_MARGIN+4,
+                w: width,
+                h: height,
+            }),
             in_player_col: false,
-            active: true,
+            container: container,
+            other_title: other_title,
+        };
+        v.update_items(state);
+        v
+    }
+
+    /// Refreshes both lists from the player's inventory and the items on
+    /// the ground under the player or inside the container.
+    pub fn update_items(&mut self, state: &GameState) {
+        let player = state.get_player();
+        self.player_items.set_entries(inv_to_entries(&player.inventory));
+        let other = match self.container {
+            Some(id) => state.container_contents(id),
+            None     => state.items_at(player.pos).cloned(),
+        };
+        self.other_items.set_entries(other.as_ref().map_or(vec![], inv_to_entries));
+    }
+
+    /// Moves the item with id `id` to the other side.
+    fn move_item(&mut self, state: &mut GameState, id: ItemId) -> Option<Command> {
+        if self.in_player_col {
+            // Stacks are dropped one item at a time
+            match self.container {
+                Some(c) => state.player_action(Action::PutItem(c, id, 1)),
+                None    => state.player_action(Action::DropItem(id, 1)),
+            }
+        } else {
+            // Containers that can't be carried are opened instead
+            let is_fixed = PickupView::ground_item(state, id)
+                .is_some_and(|x| x.contents.is_some() && x.fixed);
+            match self.container {
+                Some(c)           => state.player_action(Action::TakeItem(c, id)),
+                None if is_fixed  => return Some(Command::OpenContainer(id)),
+                None              => state.player_action(Action::GetItem(id)),
+            }
         }
+        self.update_items(state);
+        None
+    }
+
+    /// Finds the item with id `id` on the ground under the player.
+    fn ground_item(state: &GameState, id: ItemId) -> Option<&Item> {
+        state.items_at(state.get_player().pos).and_then(|x| x.get_item(id))
     }
 }
 
 impl View for PickupView {
     fn handle_command(&mut self, state: &mut GameState, com: Command) -> Option<Command> {
+        // Clicking an item in the other column switches to it
+        if let Command::Click(pos) = com {
+            if self.player_items.contains(pos) {
+                self.in_player_col = true;
+            } else if self.other_items.contains(pos) {
+                self.in_player_col = false;
+            }
+        }
+        let list = if self.in_player_col {
+            &mut self.player_items
+        } else {
+            &mut self.other_items
+        };
+        match list.handle_command(com) {
+            MenuEvent::Chosen(id) => return self.move_item(state, id),
+            MenuEvent::Moved      => return None,
+            MenuEvent::Ignored    => {}
+        }
         match com {
-            Command::Move(Dir::N) => {
-                if self.in_player_col {
-                    self.player_cursor = max(0, self.player_cursor-1);
-                } else {
-                    self.other_cursor = max(0, self.other_cursor-1);
+            Command::Move(Dir::E) => self.in_player_col = false,
+            Command::Move(Dir::W) => self.in_player_col = true,
+            Command::CloseView => return Some(Command::CloseView),
+            // Bags on the ground can be looked into without picking them up
+            Command::Open if !self.in_player_col && self.container.is_none() => {
+                let bag = self.other_items
+                    .selected()
+                    .and_then(|id| PickupView::ground_item(state, id))
+                    .filter(|x| x.contents.is_some())
+                    .map(|x| x.id);
+                if let Some(id) = bag {
+                    return Some(Command::OpenContainer(id));
                 }
-                self.other_cursor = max(self.other_cursor, 0);
-                self.player_cursor = max(self.player_cursor, 0);
             }
-            Command::Move(Dir::S) => {
-                if self.in_player_col {
-                    self.player_cursor = min(self.player_cursor+1, self.player_items.len() as i32-1);
+            Command::Examine => {
+                let id = if self.in_player_col {
+                    self.player_items.selected()
                 } else {
-                    self.other_cursor = min(self.other_cursor+1, self.other_items.len() as i32-1);
+                    self.other_items.selected()
+                };
+                if let Some(id) = id {
+                    return Some(Command::ShowItem(id));
                 }
-                self.other_cursor = max(self.other_cursor, 0);
-                self.player_cursor = max(self.player_cursor, 0);
-            }
-            Command::Move(Dir::E) => {
-                if self.in_player_col {
-                    self.in_player_col = false;
-                }
-            }
-            Command::Move(Dir::W) => {
-                if !self.in_player_col {
-                    self.in_player_col = true;
-                }
-            }
-            Command::CloseView => return Some(Command::CloseView),
-            Command::Select => {
-                if self.in_player_col && self.player_items.len() > 0 {
-                    state.player_action(Action::DropItem(self.player_cursor));
-                    self.player_cursor -= 1;
-                } else if !self.in_player_col && self.other_items.len() > 0 {
-                    state.player_action(Action::GetItem(self.other_cursor));
-                    self.other_cursor -= 1;
-                }
-                self.player_items = inv_to_list(&state.get_player().inventory);
-                self.other_items = inv_to_list(&state.get_object(self.other_idx as usize).inventory);
-                self.other_cursor = max(self.other_cursor, 0);
-                self.player_cursor = max(self.player_cursor, 0);
             }
             _ => {}
         }
         None
     }
 
+    fn child_closed(&mut self, state: &mut GameState, _result: Option<Command>) -> Option<Command> {
+        self.update_items(state);
+        None
+    }
+
+    fn accepts_hotkeys(&self) -> bool {
+        true
+    }
+
     fn render(&self, con: &mut Offscreen) {
         con.print_frame(
             MENU_MARGIN, 
@@ -167,36 +637,416 @@ impl View for PickupView {
             BackgroundFlag::Set,
             Some("Get items"));
         con.print(MENU_MARGIN+5, MENU_MARGIN+2 as i32, "Inventory");
-        for i in 0..self.player_items.len() {
-            con.print(MENU_MARGIN+5, MENU_MARGIN+4+i as i32, &self.player_items[i]);
-        }
-        con.print(SCREEN_WIDTH/2+5, MENU_MARGIN+2 as i32, "Ground");
-        for i in 0..self.other_items.len() {
-            con.print(SCREEN_WIDTH/2+5, MENU_MARGIN+4+i as i32, &self.other_items[i]);
-        }
-        for y in MENU_MARGIN+1..SCREEN_HEIGHT-MENU_MARGIN-1 {
+        self.player_items.render(con, self.in_player_col);
+        con.print(SCREEN_WIDTH/2+5, MENU_MARGIN+2 as i32, &self.other_title);
+        self.other_items.render(con, !self.in_player_col);
+        for y in MENU_MARGIN+1..SCREEN_HEIGHT-MENU_MARGIN-2 {
             con.put_char(
                 SCREEN_WIDTH/2,
                 y,
                 '|',
                 BackgroundFlag::Set);
         }
-        if self.in_player_col {
-            con.put_char(
-                MENU_MARGIN+3, 
-                MENU_MARGIN+4+self.player_cursor, 
-                '>', 
-                BackgroundFlag::Set);
-        } else {
-            con.put_char(
-                SCREEN_WIDTH/2+3, 
-                MENU_MARGIN+4+self.other_cursor, 
-                '>', 
-                BackgroundFlag::Set);
+        con.print(
+            MENU_MARGIN+4,
+            SCREEN_HEIGHT-MENU_MARGIN-2,
+            "Enter: move item  Left/right: switch side  X: examine  O: open");
+    }
+}
+
+/// What to do at the targeted position.
+#[derive(Clone, Copy)]
+pub enum Aim {
+    /// Shoot the wielded firearm
+    Shoot,
+    /// Throw the item with the given id from the inventory
+    Throw(ItemId),
+    /// Use the item with the given id from the inventory on somebody
+    Use(ItemId),
+}
+
+impl Aim {
+    /// The action that carries out the aim at position `target`.
+    pub fn action(self, target: Pos) -> Action {
+        match self {
+            Aim::Shoot      => Action::Shoot(target),
+            Aim::Throw(id)  => Action::Throw(id, target),
+            Aim::Use(id)    => Action::UseItemOn(id, target),
+        }
+    }
+}
+
+pub struct TargetingView {
+    aim: Aim,
+    range: i32,
+    origin: Pos,
+    cursor: Pos,
+    cam_pos: Pos,
+    path: Vec<Pos>,
+}
+
+impl TargetingView {
+    /// Creates a view for aiming a shot or a throw. The cursor starts at the
+    /// closest visible enemy on the screen.
+    pub fn new(state: &GameState, aim: Aim) -> Self {
+        let (origin, faction, range) = {
+            let player = state.get_player();
+            let item = match aim {
+                Aim::Shoot       => player.equipment.firearm(),
+                Aim::Throw(id)   => player.inventory.get_item(id),
+                Aim::Use(_)      => None,
+            };
+            let range = item
+                .and_then(|x| x.ranged.as_ref())
+                .map_or(THROW_RANGE, |r| r.range);
+            (player.pos, player.faction, range)
+        };
+        let cam_pos = state.cam_pos();
+        let cursor = state.others()
+            .iter()
+            .filter(|o| o.alive && faction.is_hostile(o.faction) && state.is_visible(o.pos))
+            .filter(|o| VIEWPORT.contains(o.pos - cam_pos))
+            .min_by_key(|o| origin.distance(o.pos))
+            .map_or(origin, |o| o.pos);
+        let mut v = TargetingView {
+            aim: aim,
+            range: range,
+            origin: origin,
+            cursor: cursor,
+            cam_pos: cam_pos,
+            path: vec![],
+        };
+        v.update_path(state);
+        v
+    }
+
+    fn update_path(&mut self, state: &GameState) {
+        self.path = state.projectile_path(self.origin, self.cursor, self.range);
+    }
+}
+
+impl View for TargetingView {
+    fn handle_command(&mut self, state: &mut GameState, com: Command) -> Option<Command> {
+        match com {
+            Command::Move(dir) => {
+                let new_pos = self.cursor + dir.to_pos();
+                // Only visible tiles on the screen can be targeted
+                if state.is_visible(new_pos) && VIEWPORT.contains(new_pos - self.cam_pos) {
+                    self.cursor = new_pos;
+                    self.update_path(state);
+                }
+            }
+            // Whoever opened the view carries out the action
+            Command::Select => return Some(Command::Finish),
+            Command::CloseView => return Some(Command::CloseView),
+            _ => {}
+        }
+        None
+    }
+
+    fn shows_map(&self) -> bool {
+        true
+    }
+
+    fn result(&self) -> Option<Command> {
+        Some(Command::Aimed(self.aim, self.cursor))
+    }
+
+    fn render(&self, con: &mut Offscreen) {
+        for p in &self.path {
+            let screen_pos = *p - self.cam_pos;
+            if !VIEWPORT.contains(screen_pos) {
+                continue;
+            }
+            con.set_char_background(screen_pos.x, screen_pos.y, DARK_YELLOW, BackgroundFlag::Set);
+        }
+        let screen_pos = self.cursor - self.cam_pos;
+        con.set_default_foreground(YELLOW);
+        con.put_char(screen_pos.x, screen_pos.y, 'X', BackgroundFlag::None);
+        con.set_default_foreground(WHITE);
+    }
+}
+
+fn inv_to_entries(inventory: &Inventory) -> Vec<MenuEntry<ItemId>> {
+    inventory.items
+        .iter()
+        .map(|x| MenuEntry::new(x.display_name(), x.id))
+        .collect()
+}
+
+/// Lets the player move a cursor over the map and read what is there.
+pub struct LookView {
+    cursor: Pos,
+    cam_pos: Pos,
+    description: Vec<String>,
+}
+
+impl LookView {
+    pub fn new(state: &GameState) -> Self {
+        let mut v = LookView {
+            cursor: state.get_player().pos,
+            cam_pos: state.cam_pos(),
+            description: vec![],
+        };
+        v.description = state.describe_tile(v.cursor);
+        v
+    }
+}
+
+impl View for LookView {
+    fn handle_command(&mut self, state: &mut GameState, com: Command) -> Option<Command> {
+        match com {
+            Command::Move(dir) => {
+                let new_pos = self.cursor + dir.to_pos();
+                if VIEWPORT.contains(new_pos - self.cam_pos) {
+                    self.cursor = new_pos;
+                    self.description = state.describe_tile(self.cursor);
+                }
+            }
+            Command::Select | Command::Examine => {
+                // Only items that are in view can be examined
+                if !state.is_visible(self.cursor) {
+                    return None;
+                }
+                let top = state.items_at(self.cursor).and_then(|x| x.items.last());
+                if let Some(item) = top {
+                    return Some(Command::ShowItem(item.id));
+                }
+            }
+            Command::CloseView => return Some(Command::CloseView),
+            _ => {}
+        }
+        None
+    }
+
+    fn shows_map(&self) -> bool {
+        true
+    }
+
+    fn render(&self, con: &mut Offscreen) {
+        let screen_pos = self.cursor - self.cam_pos;
+        con.set_char_background(screen_pos.x, screen_pos.y, DARK_YELLOW, BackgroundFlag::Set);
+        // The description covers the message panel, never the map
+        con.print_frame(LOG.x, LOG.y, LOG.w, LOG.h, true, BackgroundFlag::Set, Some("Look"));
+        let lines = self.description.iter().flat_map(|x| wrap_text(x, LOG.w - 4));
+        for (i, line) in lines.take((LOG.h - 2) as usize).enumerate() {
+            con.print(LOG.x + 2, LOG.y + 1 + i as i32, &line);
+        }
+    }
+}
+
+/// Message categories the log can be filtered by, in the order they are
+/// cycled through. `None` shows everything.
+const LOG_FILTERS: [Option<Category>; 6] = [
+    None,
+    Some(Category::Combat),
+    Some(Category::DamageTaken),
+    Some(Category::Loot),
+    Some(Category::Warning),
+    Some(Category::Flavor),
+];
+
+/// Shows the whole message history with the newest messages at the bottom.
+pub struct MessageLogView {
+    messages: Vec<Message>,
+    /// Index into `LOG_FILTERS`
+    filter: usize,
+    /// Number of lines scrolled up from the newest message
+    scroll: i32,
+}
+
+impl MessageLogView {
+    pub fn new(state: &GameState) -> Self {
+        MessageLogView {
+            messages: state.messages().messages().clone(),
+            filter: 0,
+            scroll: 0,
+        }
+    }
+
+    /// Messages that pass the current filter.
+    fn visible(&self) -> Vec<&Message> {
+        let filter = LOG_FILTERS[self.filter];
+        self.messages
+            .iter()
+            .filter(|x| filter.is_none_or(|c| x.category == c))
+            .collect()
+    }
+
+    /// Number of message lines that fit in the frame.
+    fn page_height() -> i32 {
+        SCREEN_HEIGHT - 4
+    }
+
+    fn max_scroll(&self) -> i32 {
+        max(0, self.visible().len() as i32 - MessageLogView::page_height())
+    }
+}
+
+impl View for MessageLogView {
+    fn handle_command(&mut self, _state: &mut GameState, com: Command) -> Option<Command> {
+        match com {
+            Command::Move(Dir::N) => self.scroll = min(self.max_scroll(), self.scroll+1),
+            Command::Move(Dir::S) => self.scroll = max(0, self.scroll-1),
+            Command::PageUp => {
+                self.scroll = min(self.max_scroll(), self.scroll + MessageLogView::page_height());
+            }
+            Command::PageDown => self.scroll = max(0, self.scroll - MessageLogView::page_height()),
+            Command::ScrollUp => self.scroll = min(self.max_scroll(), self.scroll + WHEEL_LINES),
+            Command::ScrollDown => self.scroll = max(0, self.scroll - WHEEL_LINES),
+            Command::Move(Dir::E) => {
+                self.filter = (self.filter + 1) % LOG_FILTERS.len();
+                self.scroll = 0;
+            }
+            Command::Move(Dir::W) => {
+                self.filter = (self.filter + LOG_FILTERS.len() - 1) % LOG_FILTERS.len();
+                self.scroll = 0;
+            }
+            Command::CloseView => return Some(Command::CloseView),
+            _ => {}
+        }
+        None
+    }
+
+    fn render(&self, con: &mut Offscreen) {
+        let title = format!(
+            "Messages: {}",
+            LOG_FILTERS[self.filter].map_or("all", |x| x.name()));
+        con.print_frame(
+            0,
+            0,
+            SCREEN_WIDTH,
+            SCREEN_HEIGHT,
+            true,
+            BackgroundFlag::Set,
+            Some(&title));
+        let visible = self.visible();
+        let end = visible.len() as i32 - self.scroll;
+        let start = max(0, end - MessageLogView::page_height());
+        for (i, msg) in visible[start as usize..end as usize].iter().enumerate() {
+            con.set_default_foreground(msg.severity.color());
+            con.print(2, 2+i as i32, format!("[{}] {}", msg.turn, msg.display_text()));
+        }
+        con.set_default_foreground(WHITE);
+        con.print(2, SCREEN_HEIGHT-2, "Up/down, PgUp/PgDn, wheel: scroll  Left/right: filter");
+    }
+}
+
+/// Everything there is to know about the player character, split into two
+/// columns of titled sections.
+pub struct CharacterSheetView {
+    left: Vec<(String, Vec<String>)>,
+    right: Vec<(String, Vec<String>)>,
+}
+
+impl CharacterSheetView {
+    pub fn new(state: &GameState) -> Self {
+        let player = state.get_player();
+        let base = player.stat_block;
+        let stats = player.stats();
+        let bonus = player.equipment.bonus();
+        let line = |name: &str, base: i32, total: i32| {
+            format!("{:<14}{:>3}  ({:+})", name, total, total - base)
+        };
+        let attributes = vec![
+            line("Strength", base.str, stats.str),
+            line("Agility", base.agi, stats.agi),
+            line("Constitution", base.con, stats.con),
+        ];
+        let derived = vec![
+            format!("{:<14}{}/{}", "Health", player.health, player.max_health()),
+            format!("{:<14}{}", "Max damage", player.max_damage()),
+            format!("{:<14}{:+}", "Damage bonus", player.damage_bonus()),
+            format!(
+                "{:<14}{}% vs. evasion {}",
+                "Hit chance",
+                hit_chance(stats.agi, AVERAGE_EVASION),
+                AVERAGE_EVASION),
+            format!("{:<14}{}%", "Crit chance", crit_chance(stats.agi)),
+            format!("{:<14}{}", "Evasion", player.evasion()),
+            format!("{:<14}{}", "Speed", player.speed()),
+            format!(
+                "{:<14}{:.1}/{:.1} kg",
+                "Carrying", player.carried_weight(), player.carry_capacity()),
+        ];
+        let mut equipment: Vec<String> = player.equipment.items
+            .iter()
+            .map(|x| x.name.clone())
+            .collect();
+        if equipment.is_empty() {
+            equipment.push("(nothing)".to_string());
+        }
+        if bonus != StatBlock::zero() {
+            equipment.push(String::new());
+            equipment.push(format!("Bonus: {}", describe_bonus(bonus)));
+        }
+        let locs = [
+            WearLoc::Head,
+            WearLoc::Shoulders,
+            WearLoc::Torso,
+            WearLoc::Hands,
+            WearLoc::Legs,
+        ];
+        let armor = locs
+            .iter()
+            .map(|x| format!("{:<14}{}", x.name(), player.armor_at(*x)))
+            .collect();
+        let reputation = [Faction::Wolves, Faction::Raiders, Faction::Neutral]
+            .iter()
+            .map(|x| {
+                let attitude = if x.is_hostile(player.faction) {
+                    "hostile"
+                } else {
+                    "indifferent"
+                };
+                format!("{:<14}{}", x.name(), attitude)
+            })
+            .collect();
+        CharacterSheetView {
+            left: vec![
+                ("Attributes".to_string(), attributes),
+                ("Derived".to_string(), derived),
+            ],
+            right: vec![
+                ("Equipment".to_string(), equipment),
+                ("Armor".to_string(), armor),
+                ("Factions".to_string(), reputation),
+            ],
+        }
+    }
+
+    fn render_column(con: &mut Offscreen, x: i32, sections: &[(String, Vec<String>)]) {
+        let mut y = MENU_MARGIN + 2;
+        for (title, lines) in sections.iter() {
+            con.set_default_foreground(LIGHT_BLUE);
+            con.print(x, y, title);
+            con.set_default_foreground(WHITE);
+            for (i, line) in lines.iter().enumerate() {
+                con.print(x + 1, y + 1 + i as i32, line);
+            }
+            y += lines.len() as i32 + 2;
         }
     }
 }
 
-fn inv_to_list(inventory: &Inventory) -> Vec<String> {
-    inventory.items.iter().map(|x| x.name.clone()).collect()
+impl View for CharacterSheetView {
+    fn handle_command(&mut self, _state: &mut GameState, com: Command) -> Option<Command> {
+        match com {
+            Command::CloseView | Command::Select => Some(Command::CloseView),
+            _                                    => None,
+        }
+    }
+
+    fn render(&self, con: &mut Offscreen) {
+        con.print_frame(
+            MENU_MARGIN,
+            MENU_MARGIN,
+            SCREEN_WIDTH-MENU_MARGIN*2,
+            SCREEN_HEIGHT-MENU_MARGIN*2,
+            true,
+            BackgroundFlag::Set,
+            Some("Character"));
+        CharacterSheetView::render_column(con, MENU_MARGIN+3, &self.left);
+        CharacterSheetView::render_column(con, SCREEN_WIDTH/2+2, &self.right);
+    }
 }